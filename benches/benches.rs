@@ -5,7 +5,7 @@ extern crate pgn_reader;
 use std::collections::HashMap;
 
 use bencher::{Bencher, black_box};
-use pgn_reader::{Reader, Visitor, Skip, Nag};
+use pgn_reader::{Reader, Visitor, Skip, Nag, RawHeader};
 
 const FIXTURE: &[u8] = include_bytes!("fixture.pgn");
 
@@ -38,7 +38,7 @@ fn bench_nag(b: &mut Bencher) {
 }
 
 struct HeaderVisitor<'a> {
-    headers: HashMap<&'a [u8], &'a [u8]>,
+    headers: HashMap<&'a [u8], RawHeader<'a>>,
 }
 
 impl<'a> HeaderVisitor<'a> {
@@ -48,9 +48,9 @@ impl<'a> HeaderVisitor<'a> {
 }
 
 impl<'pgn> Visitor<'pgn> for HeaderVisitor<'pgn> {
-    type Result = HashMap<&'pgn [u8], &'pgn [u8]>;
+    type Result = HashMap<&'pgn [u8], RawHeader<'pgn>>;
 
-    fn header(&mut self, key: &'pgn [u8], value: &'pgn [u8]) {
+    fn header(&mut self, key: &'pgn [u8], value: RawHeader<'pgn>) {
         self.headers.insert(key, value);
     }
 
@@ -70,8 +70,8 @@ fn bench_headers(b: &mut Bencher) {
             .read_game()
             .unwrap();
 
-        assert_eq!(headers[&b"White"[..]], b"revoof");
-        assert_eq!(headers[&b"Black"[..]], b"Servasky");
+        assert_eq!(headers[&b"White"[..]].as_bytes(), b"revoof");
+        assert_eq!(headers[&b"Black"[..]].as_bytes(), b"Servasky");
     });
 }
 
@@ -1,14 +1,19 @@
 // Counts games, moves and other tokens in PGNs.
 // Usage: cargo run --release --example stats -- [PGN]...
+// Reads from stdin if the PGN is given as -. Compressed PGNs are supported
+// with --features decompress.
 
 extern crate pgn_reader;
 extern crate memmap;
 extern crate madvise;
 
 use std::env;
+use std::io::{self, BufRead};
 use std::fs::File;
 
-use pgn_reader::{Reader, Visitor, San, Nag, Outcome};
+use pgn_reader::{Reader, BufferedReader, Visitor, RawHeader, SanPlus, Nag, Outcome};
+#[cfg(feature = "decompress")]
+use pgn_reader::{Compression, Decompressor};
 use memmap::Mmap;
 use madvise::{AccessPattern, AdviseMemory};
 
@@ -38,11 +43,11 @@ impl<'pgn> Visitor<'pgn> for Stats {
         }
     }
 
-    fn header(&mut self, _key: &'pgn [u8], _value: &'pgn [u8]) {
+    fn header(&mut self, _key: &'pgn [u8], _value: RawHeader<'pgn>) {
         self.headers += 1;
     }
 
-    fn san(&mut self, _san: San) {
+    fn san(&mut self, _san_plus: SanPlus) {
         self.sans += 1;
     }
 
@@ -58,19 +63,49 @@ impl<'pgn> Visitor<'pgn> for Stats {
         self.variations += 1;
     }
 
-    fn outcome(&mut self, _outcome: Outcome) {
+    fn outcome(&mut self, _outcome: Option<Outcome>) {
         self.outcomes += 1;
     }
 }
 
+#[cfg(feature = "decompress")]
+fn is_compressed(pgn: &[u8]) -> bool {
+    Compression::detect(pgn) != Compression::None
+}
+
+#[cfg(not(feature = "decompress"))]
+fn is_compressed(_pgn: &[u8]) -> bool {
+    false
+}
+
+#[cfg(feature = "decompress")]
+fn decompress<R: BufRead>(inner: R) -> Decompressor<R> {
+    Decompressor::new(inner).expect("decompress")
+}
+
+#[cfg(not(feature = "decompress"))]
+fn decompress<R: BufRead>(inner: R) -> R {
+    inner
+}
+
 fn main() {
     for arg in env::args().skip(1) {
-        let file = File::open(&arg).expect("fopen");
-        let pgn = unsafe { Mmap::map(&file).expect("mmap") };
-        pgn.advise_memory_access(AccessPattern::Sequential).expect("madvise");
-
         let mut stats = Stats::new();
-        Reader::new(&mut stats, &pgn[..]).read_all();
+
+        if arg == "-" {
+            let stdin = io::stdin();
+            BufferedReader::new(&mut stats, decompress(stdin.lock())).read_all().expect("read stdin");
+        } else {
+            let file = File::open(&arg).expect("fopen");
+            let pgn = unsafe { Mmap::map(&file).expect("mmap") };
+            pgn.advise_memory_access(AccessPattern::Sequential).expect("madvise");
+
+            if is_compressed(&pgn) {
+                BufferedReader::new(&mut stats, decompress(&pgn[..])).read_all().expect("decompress");
+            } else {
+                Reader::new(&mut stats, &pgn[..]).read_all();
+            }
+        }
 
         println!("{}: {:?}", arg, stats);
     }
@@ -1,64 +1,50 @@
 // Validates moves in PGNs.
 // Usage: cargo run --release --example validate -- [PGN]...
+// Compressed PGNs are supported with --features decompress.
 
 extern crate pgn_reader;
 extern crate memmap;
 extern crate madvise;
 extern crate shakmaty;
 
-use pgn_reader::{Visitor, Skip, Reader, San};
+use pgn_reader::{Visitor, Skip, Reader, Span, Legal, LegalVisitor, LegalError};
 
-use shakmaty::{Chess, Position};
-use shakmaty::fen::Fen;
+use shakmaty::Chess;
 
 use memmap::Mmap;
 use madvise::{AccessPattern, AdviseMemory};
 
 use std::env;
 use std::fs::File;
+#[cfg(feature = "decompress")]
+use std::io::Read;
 
-struct Validator {
+#[cfg(feature = "decompress")]
+use pgn_reader::{Compression, Decompressor};
+
+struct Validator<'pgn> {
+    pgn: &'pgn [u8],
     games: usize,
-    pos: Chess,
+    span: Span,
     success: bool,
 }
 
-impl Validator {
-    fn new() -> Validator {
-        Validator { games: 0, pos: Chess::default(), success: true }
+impl<'pgn> Validator<'pgn> {
+    fn new(pgn: &'pgn [u8]) -> Validator<'pgn> {
+        Validator { pgn, games: 0, span: Span::default(), success: true }
     }
 }
 
-impl<'pgn> Visitor<'pgn> for Validator {
+impl<'pgn> Visitor<'pgn> for Validator<'pgn> {
     type Result = bool;
 
     fn begin_game(&mut self) {
         self.games += 1;
-        self.pos = Chess::default();
         self.success = true;
     }
 
-    fn header(&mut self, key: &'pgn [u8], value: &'pgn [u8]) {
-        // Support games from a non-standard starting position.
-        if key == b"FEN" {
-            let fen = match Fen::from_bytes(value) {
-                Ok(fen) => fen,
-                Err(err) => {
-                    eprintln!("invalid fen header in game {}: {} ({:?})", self.games, err, value);
-                    self.success = false;
-                    return;
-                },
-            };
-
-            self.pos = match fen.position() {
-                Ok(pos) => pos,
-                Err(err) => {
-                    eprintln!("illegal fen header in game {}: {} ({})", self.games, err, fen);
-                    self.success = false;
-                    return;
-                },
-            };
-        }
+    fn span(&mut self, span: Span) {
+        self.span = span;
     }
 
     fn end_headers(&mut self) -> Skip {
@@ -69,16 +55,8 @@ impl<'pgn> Visitor<'pgn> for Validator {
         Skip(true) // stay in the mainline
     }
 
-    fn san(&mut self, san: San) {
-        if self.success {
-            match san.to_move(&self.pos) {
-                Ok(m) => self.pos.play_unchecked(&m),
-                Err(err) => {
-                    eprintln!("error in game {}: {} {}", self.games, err, san);
-                    self.success = false;
-                },
-            }
-        }
+    fn skip_movetext(&mut self) -> Skip {
+        Skip(!self.success)
     }
 
     fn end_game(&mut self, _game: &'pgn [u8]) -> Self::Result {
@@ -86,6 +64,29 @@ impl<'pgn> Visitor<'pgn> for Validator {
     }
 }
 
+impl<'pgn> LegalVisitor<'pgn> for Validator<'pgn> {
+    fn illegal_move(&mut self, error: LegalError, _pos: &Chess) {
+        let token = &self.pgn[self.span.start..self.span.end];
+        eprintln!("{} in game {} at {}: {}", error, self.games, self.span.location, String::from_utf8_lossy(token));
+        self.success = false;
+    }
+}
+
+#[cfg(feature = "decompress")]
+fn decompress(pgn: &[u8]) -> Option<Vec<u8>> {
+    if Compression::detect(pgn) == Compression::None {
+        return None;
+    }
+    let mut buf = Vec::new();
+    Decompressor::new(pgn).expect("decompress").read_to_end(&mut buf).expect("decompress");
+    Some(buf)
+}
+
+#[cfg(not(feature = "decompress"))]
+fn decompress(_pgn: &[u8]) -> Option<Vec<u8>> {
+    None
+}
+
 fn main() {
     let mut success = true;
 
@@ -94,8 +95,12 @@ fn main() {
         let pgn = unsafe { Mmap::map(&file).expect("mmap") };
         pgn.advise_memory_access(AccessPattern::Sequential).expect("madvise");
 
-        let mut validator = Validator::new();
-        success &= Reader::new(&mut validator, &pgn[..]).into_iter().all(|s| s);
+        // Decompress in memory, so that errors can be located in the text.
+        let decompressed = decompress(&pgn[..]);
+        let pgn = decompressed.as_ref().map_or(&pgn[..], |buf| &buf[..]);
+
+        let mut validator = Legal::new(Validator::new(pgn));
+        success &= Reader::new(&mut validator, pgn).into_iter().all(|s| s);
 
         println!("{}: {}", arg, if success { "success" } else { "errors" });
     }
@@ -0,0 +1,254 @@
+// This file is part of the pgn-reader library.
+// Copyright (C) 2017 Niklas Fiekas <niklas.fiekas@backscattering.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+use std::future::Future;
+use std::task::{Context, Poll};
+
+use tokio::io::{AsyncRead, ReadBuf};
+use futures_core::Stream;
+
+use {Visitor, Notation};
+use buffered::GameBuffer;
+
+/// Number of bytes to read from the underlying reader at once.
+const CHUNK: usize = 8 * 1024;
+
+/// Reads a PGN from an asynchronous stream, like [`BufferedReader`] does for
+/// a blocking one.
+///
+/// Games are yielded as a [`Stream`]. Memory usage is bounded by the size of
+/// the largest game. Requires the `async` feature.
+///
+/// # Examples
+///
+/// ```
+/// extern crate futures;
+/// extern crate pgn_reader;
+///
+/// use futures::StreamExt;
+/// use pgn_reader::{AsyncReader, Visitor};
+///
+/// struct GameCounter;
+///
+/// impl<'pgn> Visitor<'pgn> for GameCounter {
+///     type Result = ();
+///
+///     fn end_game(&mut self, _game: &'pgn [u8]) { }
+/// }
+///
+/// fn main() {
+///     // Any tokio::io::AsyncRead, for example a request body.
+///     let pgn = &b"1. e4 e5 *\n\n1. d4 d5 *"[..];
+///
+///     let mut counter = GameCounter;
+///     let games = AsyncReader::new(&mut counter, pgn).count();
+///
+///     assert_eq!(futures::executor::block_on(games), 2);
+/// }
+/// ```
+///
+/// [`BufferedReader`]: struct.BufferedReader.html
+/// [`Stream`]: https://docs.rs/futures-core/0.3/futures_core/stream/trait.Stream.html
+pub struct AsyncReader<'a, R, V> where V: 'a {
+    visitor: &'a mut V,
+    inner: R,
+    buffer: GameBuffer,
+}
+
+impl<'a, R, V> fmt::Debug for AsyncReader<'a, R, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AsyncReader").finish()
+    }
+}
+
+impl<'a, R: AsyncRead + Unpin, V> AsyncReader<'a, R, V> {
+    /// Creates a new asynchronous reader with a custom [`Visitor`].
+    ///
+    /// [`Visitor`]: trait.Visitor.html
+    pub fn new(visitor: &'a mut V, inner: R) -> AsyncReader<'a, R, V> {
+        AsyncReader { visitor, inner, buffer: GameBuffer::new() }
+    }
+
+    /// Sets the piece letters used in the movetext. Defaults to standard
+    /// English notation.
+    pub fn notation(&mut self, notation: Notation) -> &mut AsyncReader<'a, R, V> {
+        self.buffer.notation = notation;
+        self
+    }
+
+    /// Enables recovery from runaway comments and header lines, like
+    /// [`Reader::recover`]. Disabled by default.
+    ///
+    /// [`Reader::recover`]: struct.Reader.html#method.recover
+    pub fn recover(&mut self, recover: bool) -> &mut AsyncReader<'a, R, V> {
+        self.buffer.recover = recover;
+        self
+    }
+
+    /// Read the next game, resolving to the result from the visitor, or
+    /// `None` if there was no further game or the visitor requested to stop.
+    ///
+    /// # Errors
+    ///
+    /// Resolves to any I/O error of the underlying reader. No visitor
+    /// methods are called for the game that was being read.
+    pub fn read_game<'r>(&'r mut self) -> ReadGame<'r, 'a, R, V> {
+        ReadGame { reader: self }
+    }
+
+    /// Attempts to read the next game, registering the current task for
+    /// wakeup if more input is needed.
+    pub fn poll_read_game<T>(&mut self, cx: &mut Context) -> Poll<io::Result<Option<T>>>
+        where V: for<'pgn> Visitor<'pgn, Result=T>
+    {
+        loop {
+            if let Some(result) = self.buffer.read_game(self.visitor) {
+                return Poll::Ready(Ok(result));
+            }
+
+            while self.buffer.wants_more() {
+                let mut chunk = [0; CHUNK];
+                let mut buf = ReadBuf::new(&mut chunk);
+                match Pin::new(&mut self.inner).poll_read(cx, &mut buf) {
+                    Poll::Ready(Ok(())) => self.buffer.extend(buf.filled()),
+                    Poll::Ready(Err(ref err)) if err.kind() == io::ErrorKind::Interrupted => continue,
+                    Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                    Poll::Pending => return Poll::Pending,
+                }
+            }
+        }
+    }
+
+    /// Tests if reading was stopped by the visitor.
+    pub fn stopped(&self) -> bool {
+        self.buffer.stopped
+    }
+
+    /// Gets the byte offset of the next game in the input. After reading
+    /// was stopped, this is where reading could be resumed.
+    pub fn position(&self) -> usize {
+        self.buffer.position()
+    }
+
+    /// Gets the underlying reader. Bytes that were already buffered, but
+    /// not yet parsed, are lost.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<'a, R: AsyncRead + Unpin, V, T> Stream for AsyncReader<'a, R, V>
+    where V: for<'pgn> Visitor<'pgn, Result=T>
+{
+    type Item = io::Result<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        self.get_mut().poll_read_game(cx).map(|result| match result {
+            Ok(Some(game)) => Some(Ok(game)),
+            Ok(None) => None,
+            Err(err) => Some(Err(err)),
+        })
+    }
+}
+
+/// Future returned by [`AsyncReader::read_game()`].
+///
+/// [`AsyncReader::read_game()`]: struct.AsyncReader.html#method.read_game
+pub struct ReadGame<'r, 'a: 'r, R: 'r, V: 'a> {
+    reader: &'r mut AsyncReader<'a, R, V>,
+}
+
+impl<'r, 'a, R, V> fmt::Debug for ReadGame<'r, 'a, R, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ReadGame").finish()
+    }
+}
+
+impl<'r, 'a, R: AsyncRead + Unpin, V, T> Future for ReadGame<'r, 'a, R, V>
+    where V: for<'pgn> Visitor<'pgn, Result=T>
+{
+    type Output = io::Result<Option<T>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        self.get_mut().reader.poll_read_game(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::task::Waker;
+    use SanPlus;
+
+    struct Moves(usize);
+
+    impl<'pgn> Visitor<'pgn> for Moves {
+        type Result = usize;
+
+        fn san(&mut self, _san_plus: SanPlus) {
+            self.0 += 1;
+        }
+
+        fn end_game(&mut self, _game: &'pgn [u8]) -> usize {
+            ::std::mem::replace(&mut self.0, 0)
+        }
+    }
+
+    /// Yields the input in tiny pieces, returning `Pending` in between.
+    struct Trickle<'a> {
+        pgn: &'a [u8],
+        ready: bool,
+    }
+
+    impl<'a> AsyncRead for Trickle<'a> {
+        fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context, buf: &mut ReadBuf) -> Poll<io::Result<()>> {
+            if !self.ready {
+                self.ready = true;
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+
+            self.ready = false;
+            let n = ::std::cmp::min(3, self.pgn.len());
+            buf.put_slice(&self.pgn[..n]);
+            self.pgn = &self.pgn[n..];
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[test]
+    fn test_trickle() {
+        let pgn = b"1. e4 { comment\n\nwith blank line } e5 2. Nf3 *\n\n[Event \"?\"]\n1. d4 *";
+        let mut visitor = Moves(0);
+        let mut reader = AsyncReader::new(&mut visitor, Trickle { pgn, ready: false });
+
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut games = Vec::new();
+        loop {
+            match reader.poll_read_game(&mut cx) {
+                Poll::Ready(Ok(Some(moves))) => games.push(moves),
+                Poll::Ready(Ok(None)) => break,
+                Poll::Ready(Err(err)) => panic!("{}", err),
+                Poll::Pending => continue,
+            }
+        }
+
+        assert_eq!(games, vec![3, 1]);
+    }
+}
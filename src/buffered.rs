@@ -0,0 +1,363 @@
+// This file is part of the pgn-reader library.
+// Copyright (C) 2017 Niklas Fiekas <niklas.fiekas@backscattering.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt;
+use std::cmp::max;
+use std::io::{self, BufRead};
+
+use {Reader, Skip, Visitor, Notation, LineCounter};
+
+/// Minimum number of bytes to pull from the underlying reader at once.
+const MIN_CHUNK: usize = 16 * 1024;
+
+/// Visitor that skips over everything. Used to find the end of a game
+/// before handing it to the real visitor.
+struct Boundary;
+
+impl<'pgn> Visitor<'pgn> for Boundary {
+    type Result = ();
+
+    fn end_headers(&mut self) -> Skip {
+        Skip(true)
+    }
+
+    fn end_game(&mut self, _game: &'pgn [u8]) { }
+}
+
+/// Reads a PGN from a stream, like [`Reader`] does for a byte slice.
+///
+/// Bytes are pulled from any [`BufRead`] into an internal buffer. The buffer
+/// is refilled at game boundaries and grows as needed, so that each game is
+/// presented to the [`Visitor`] as one contiguous slice. Consequently the
+/// visitor must work with slices of any lifetime and can not keep borrowed
+/// data across games.
+///
+/// To find where a game ends before any visitor methods are called, each
+/// game is scanned twice: once skipping its movetext, and once for the
+/// visitor. Use [`Reader`] directly when the whole input is already in
+/// memory.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{BufferedReader, Visitor};
+///
+/// struct GameCounter;
+///
+/// impl<'pgn> Visitor<'pgn> for GameCounter {
+///     type Result = ();
+///
+///     fn end_game(&mut self, _game: &'pgn [u8]) { }
+/// }
+///
+/// # fn try_main() -> std::io::Result<()> {
+/// // Any io::BufRead, for example stdin.lock().
+/// let pgn = &b"1. e4 e5 *\n\n1. d4 d5 *"[..];
+///
+/// let mut counter = GameCounter;
+/// let mut games = 0;
+/// for game in BufferedReader::new(&mut counter, pgn) {
+///     game?;
+///     games += 1;
+/// }
+///
+/// assert_eq!(games, 2);
+/// # Ok(())
+/// # }
+/// #
+/// # fn main() {
+/// #     try_main().unwrap();
+/// # }
+/// ```
+///
+/// [`Reader`]: struct.Reader.html
+/// [`BufRead`]: https://doc.rust-lang.org/std/io/trait.BufRead.html
+/// [`Visitor`]: trait.Visitor.html
+pub struct BufferedReader<'a, R, V> where V: 'a {
+    visitor: &'a mut V,
+    inner: R,
+    buffer: GameBuffer,
+}
+
+impl<'a, R, V> fmt::Debug for BufferedReader<'a, R, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BufferedReader").finish()
+    }
+}
+
+impl<'a, R: BufRead, V> BufferedReader<'a, R, V> {
+    /// Creates a new buffered reader with a custom [`Visitor`].
+    ///
+    /// [`Visitor`]: trait.Visitor.html
+    pub fn new(visitor: &'a mut V, inner: R) -> BufferedReader<'a, R, V> {
+        BufferedReader { visitor, inner, buffer: GameBuffer::new() }
+    }
+
+    /// Sets the piece letters used in the movetext. Defaults to standard
+    /// English notation.
+    pub fn notation(&mut self, notation: Notation) -> &mut BufferedReader<'a, R, V> {
+        self.buffer.notation = notation;
+        self
+    }
+
+    /// Enables recovery from runaway comments and header lines, like
+    /// [`Reader::recover`]. Disabled by default.
+    ///
+    /// [`Reader::recover`]: struct.Reader.html#method.recover
+    pub fn recover(&mut self, recover: bool) -> &mut BufferedReader<'a, R, V> {
+        self.buffer.recover = recover;
+        self
+    }
+
+    /// Read the next game, returning the result from the visitor, or `None`
+    /// if there was no further game or the visitor requested to stop.
+    ///
+    /// # Errors
+    ///
+    /// Returns any I/O error of the underlying reader. No visitor methods
+    /// are called for the game that was being read.
+    pub fn read_game<T>(&mut self) -> io::Result<Option<T>>
+        where V: for<'pgn> Visitor<'pgn, Result=T>
+    {
+        loop {
+            if let Some(result) = self.buffer.read_game(self.visitor) {
+                return Ok(result);
+            }
+
+            while self.buffer.wants_more() {
+                let n = match self.inner.fill_buf() {
+                    Ok(chunk) => {
+                        self.buffer.extend(chunk);
+                        chunk.len()
+                    },
+                    Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(err) => return Err(err),
+                };
+                self.inner.consume(n);
+            }
+        }
+    }
+
+    /// Reads all games, or until the visitor requests to stop.
+    ///
+    /// # Errors
+    ///
+    /// Stops at the first I/O error of the underlying reader.
+    pub fn read_all<T>(&mut self) -> io::Result<()>
+        where V: for<'pgn> Visitor<'pgn, Result=T>
+    {
+        while self.read_game()?.is_some() { }
+        Ok(())
+    }
+
+    /// Tests if reading was stopped by the visitor.
+    pub fn stopped(&self) -> bool {
+        self.buffer.stopped
+    }
+
+    /// Gets the byte offset of the next game in the input. After reading
+    /// was stopped, this is where reading could be resumed.
+    pub fn position(&self) -> usize {
+        self.buffer.position()
+    }
+
+    /// Gets the underlying reader. Bytes that were already buffered, but
+    /// not yet parsed, are lost.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+/// Collects input until it contains a complete game.
+pub(crate) struct GameBuffer {
+    buf: Vec<u8>,
+    start: usize,
+    discarded: usize,
+    lines: LineCounter,
+    target: usize,
+    eof: bool,
+    pub stopped: bool,
+    pub notation: Notation,
+    pub recover: bool,
+}
+
+impl GameBuffer {
+    pub fn new() -> GameBuffer {
+        GameBuffer {
+            buf: Vec::with_capacity(MIN_CHUNK),
+            start: 0,
+            discarded: 0,
+            lines: LineCounter::default(),
+            target: 0,
+            eof: false,
+            stopped: false,
+            notation: Notation::default(),
+            recover: false,
+        }
+    }
+
+    /// Tests if more input should be added before trying to read the next
+    /// game.
+    pub fn wants_more(&self) -> bool {
+        !self.eof && !self.stopped && self.buf.len() < self.target
+    }
+
+    /// Gets the byte offset of the next unparsed game.
+    pub fn position(&self) -> usize {
+        self.discarded + self.start
+    }
+
+    /// Appends input. An empty chunk signals the end of the input.
+    pub fn extend(&mut self, chunk: &[u8]) {
+        if chunk.is_empty() {
+            self.eof = true;
+        } else {
+            self.buf.extend_from_slice(chunk);
+        }
+    }
+
+    /// Parses the next game, if it is completely buffered or there is no
+    /// further input. Otherwise returns `None` to ask for more input.
+    pub fn read_game<V, T>(&mut self, visitor: &mut V) -> Option<Option<T>>
+        where V: for<'pgn> Visitor<'pgn, Result=T>
+    {
+        if self.stopped {
+            return Some(None);
+        } else if self.wants_more() {
+            return None;
+        }
+
+        let (len, complete) = game_len(&self.buf[self.start..], self.recover);
+        if complete || self.eof {
+            let end = self.start + len;
+            let mut reader = Reader::with_offset(visitor, &self.buf[self.start..end], self.lines);
+            reader.notation(self.notation).recover(self.recover);
+            let result = reader.read_game();
+            self.stopped = reader.stopped();
+            self.lines = reader.origin();
+            self.start = end;
+            return Some(result);
+        }
+
+        // Discard games that were already parsed. Then make sure to at least
+        // double the buffered data, to avoid rescanning a long game over and
+        // over.
+        self.buf.drain(..self.start);
+        self.discarded += self.start;
+        self.start = 0;
+        self.target = self.buf.len() + max(MIN_CHUNK, self.buf.len());
+        None
+    }
+}
+
+/// Finds the length of the first game (including trailing whitespace) in a
+/// buffer, and whether it is known to be complete, i.e. the start of the
+/// following game is already in the buffer.
+fn game_len(pgn: &[u8], recover: bool) -> (usize, bool) {
+    let mut boundary = Boundary;
+    let mut reader = Reader::new(&mut boundary, pgn);
+    reader.recover(recover);
+    reader.read_game();
+    (pgn.len() - reader.pgn.len(), !reader.pgn.is_empty())
+}
+
+impl<'a, R: BufRead, V, T> IntoIterator for BufferedReader<'a, R, V>
+    where V: for<'pgn> Visitor<'pgn, Result=T>
+{
+    type Item = io::Result<T>;
+    type IntoIter = BufferedIter<'a, R, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        BufferedIter { reader: self }
+    }
+}
+
+/// View a [`BufferedReader`] as an iterator.
+///
+/// [`BufferedReader`]: struct.BufferedReader.html
+pub struct BufferedIter<'a, R, V> where V: 'a {
+    reader: BufferedReader<'a, R, V>,
+}
+
+impl<'a, R, V> fmt::Debug for BufferedIter<'a, R, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BufferedIter").finish()
+    }
+}
+
+impl<'a, R: BufRead, V, T> Iterator for BufferedIter<'a, R, V>
+    where V: for<'pgn> Visitor<'pgn, Result=T>
+{
+    type Item = io::Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.reader.read_game() {
+            Ok(Some(result)) => Some(Ok(result)),
+            Ok(None) => None,
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::BufReader;
+    use {SanPlus, Outcome};
+
+    #[derive(Default)]
+    struct Moves {
+        sans: usize,
+        outcome: Option<Outcome>,
+    }
+
+    impl<'pgn> Visitor<'pgn> for Moves {
+        type Result = (usize, Option<Outcome>);
+
+        fn san(&mut self, _san_plus: SanPlus) {
+            self.sans += 1;
+        }
+
+        fn outcome(&mut self, outcome: Option<Outcome>) {
+            self.outcome = outcome;
+        }
+
+        fn end_game(&mut self, _game: &'pgn [u8]) -> Self::Result {
+            (::std::mem::replace(&mut self.sans, 0), self.outcome.take())
+        }
+    }
+
+    #[test]
+    fn test_games_larger_than_chunks() {
+        let pgn = b"[Event \"A\"]\n\n1. e4 { long\n\ncomment } e5 1-0\n\n\
+                    [Event \"B\"]\n1. d4 0-1\n\n1. c4 c5 2. Nc3 1/2-1/2\n";
+
+        let mut visitor = Moves::default();
+        let expected: Vec<_> = Reader::new(&mut visitor, &pgn[..]).into_iter().collect();
+        assert_eq!(expected.len(), 3);
+
+        for capacity in 1..8 {
+            let inner = BufReader::with_capacity(capacity, &pgn[..]);
+            let mut visitor = Moves::default();
+            let games = BufferedReader::new(&mut visitor, inner)
+                .into_iter()
+                .collect::<io::Result<Vec<_>>>()
+                .expect("read from slice");
+
+            assert_eq!(games, expected);
+        }
+    }
+}
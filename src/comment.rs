@@ -0,0 +1,327 @@
+// This file is part of the pgn-reader library.
+// Copyright (C) 2017 Niklas Fiekas <niklas.fiekas@backscattering.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::borrow::Cow;
+use std::time::Duration;
+
+use btoi::{btoi, btou};
+use memchr;
+
+use {Square, is_space};
+
+/// A comment split into embedded commands and the remaining free text.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use pgn_reader::{Comment, Command, Eval};
+///
+/// let comment = Comment::parse(b" [%eval -0.3] Inaccuracy. [%clk 0:02:57.5] ");
+///
+/// assert_eq!(comment.commands, vec![
+///     Command::Eval { eval: Eval::Centipawns(-30), depth: None },
+///     Command::Clock(Duration::from_millis(177_500)),
+/// ]);
+/// assert_eq!(comment.text, &b"Inaccuracy."[..]);
+/// ```
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Comment<'pgn> {
+    /// Commands like `[%clk 0:03:00]`, in the order they appear.
+    pub commands: Vec<Command<'pgn>>,
+    /// The text around the commands, trimmed. Pieces of text between
+    /// commands are joined by a single space.
+    pub text: Cow<'pgn, [u8]>,
+}
+
+impl<'pgn> Comment<'pgn> {
+    /// Parses the body of a comment, as passed to [`Visitor::comment`].
+    ///
+    /// [`Visitor::comment`]: trait.Visitor.html#method.comment
+    pub fn parse(comment: &'pgn [u8]) -> Comment<'pgn> {
+        let mut commands = Vec::new();
+        let mut texts = Vec::new();
+
+        let mut pos = 0;
+        while let Some(start) = find_command(&comment[pos..]).map(|p| pos + p) {
+            let end = match memchr::memchr(b']', &comment[start..]) {
+                Some(delta) => start + delta,
+                None => break,
+            };
+
+            texts.push(trim(&comment[pos..start]));
+            commands.push(Command::parse(&comment[start + 2..end]));
+            pos = end + 1;
+        }
+        texts.push(trim(&comment[pos..]));
+        texts.retain(|text| !text.is_empty());
+
+        let text = match texts.len() {
+            0 => Cow::Borrowed(&b""[..]),
+            1 => Cow::Borrowed(texts[0]),
+            _ => Cow::Owned(texts.join(&b' ')),
+        };
+
+        Comment { commands, text }
+    }
+}
+
+/// A command embedded in a comment, like `[%clk 0:03:00]`.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum Command<'pgn> {
+    /// Remaining time on the clock (`%clk`).
+    Clock(Duration),
+    /// Time spent on the move (`%emt`).
+    ElapsedMoveTime(Duration),
+    /// Engine evaluation (`%eval`), possibly with search depth.
+    Eval { eval: Eval, depth: Option<u32> },
+    /// Coloured squares (`%csl`).
+    Highlights(Vec<Highlight>),
+    /// Coloured arrows (`%cal`).
+    Arrows(Vec<Arrow>),
+    /// An unknown command, or a known command with invalid arguments.
+    Other { name: &'pgn [u8], args: &'pgn [u8] },
+}
+
+impl<'pgn> Command<'pgn> {
+    /// Parses the contents of `[% ...]`.
+    fn parse(command: &'pgn [u8]) -> Command<'pgn> {
+        let command = trim(command);
+        let split = command.iter().position(|c| is_space(*c)).unwrap_or(command.len());
+        let (name, args) = (&command[..split], trim(&command[split..]));
+
+        let parsed = match name {
+            b"clk" => parse_duration(args).map(Command::Clock),
+            b"emt" => parse_duration(args).map(Command::ElapsedMoveTime),
+            b"eval" => parse_eval(args),
+            b"csl" => parse_list(args, Highlight::from_bytes).map(Command::Highlights),
+            b"cal" => parse_list(args, Arrow::from_bytes).map(Command::Arrows),
+            _ => None,
+        };
+
+        parsed.unwrap_or(Command::Other { name, args })
+    }
+}
+
+/// An engine evaluation from the point of view of white.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum Eval {
+    /// Advantage in hundredths of a pawn, like `0.17` or `-1.5`.
+    Centipawns(i32),
+    /// Mate in the given number of moves, like `#3`, or `#-3` if black
+    /// mates.
+    Mate(i32),
+}
+
+/// The colour of a [`Highlight`] or an [`Arrow`].
+///
+/// [`Highlight`]: struct.Highlight.html
+/// [`Arrow`]: struct.Arrow.html
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum AnnotationColor {
+    Green,
+    Red,
+    Yellow,
+    Blue,
+}
+
+impl AnnotationColor {
+    fn from_byte(b: u8) -> Option<AnnotationColor> {
+        match b {
+            b'G' => Some(AnnotationColor::Green),
+            b'R' => Some(AnnotationColor::Red),
+            b'Y' => Some(AnnotationColor::Yellow),
+            b'B' => Some(AnnotationColor::Blue),
+            _ => None,
+        }
+    }
+}
+
+/// A coloured square, like `Ge4`.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct Highlight {
+    pub color: AnnotationColor,
+    pub square: Square,
+}
+
+impl Highlight {
+    fn from_bytes(s: &[u8]) -> Option<Highlight> {
+        match s.split_first() {
+            Some((&color, square)) => Some(Highlight {
+                color: AnnotationColor::from_byte(color)?,
+                square: Square::from_bytes(square).ok()?,
+            }),
+            None => None,
+        }
+    }
+}
+
+/// A coloured arrow, like `Re2e4`.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct Arrow {
+    pub color: AnnotationColor,
+    pub from: Square,
+    pub to: Square,
+}
+
+impl Arrow {
+    fn from_bytes(s: &[u8]) -> Option<Arrow> {
+        if s.len() != 5 {
+            return None;
+        }
+
+        Some(Arrow {
+            color: AnnotationColor::from_byte(s[0])?,
+            from: Square::from_bytes(&s[1..3]).ok()?,
+            to: Square::from_bytes(&s[3..5]).ok()?,
+        })
+    }
+}
+
+fn find_command(s: &[u8]) -> Option<usize> {
+    let mut pos = 0;
+    while let Some(delta) = memchr::memchr(b'[', &s[pos..]) {
+        pos += delta;
+        if s[pos + 1..].starts_with(b"%") {
+            return Some(pos);
+        }
+        pos += 1;
+    }
+    None
+}
+
+fn trim(mut s: &[u8]) -> &[u8] {
+    while let Some((&first, rest)) = s.split_first() {
+        if !is_space(first) {
+            break;
+        }
+        s = rest;
+    }
+    while let Some((&last, rest)) = s.split_last() {
+        if !is_space(last) {
+            break;
+        }
+        s = rest;
+    }
+    s
+}
+
+/// Parses a comma separated list of items.
+fn parse_list<T, F>(s: &[u8], item: F) -> Option<Vec<T>>
+    where F: Fn(&[u8]) -> Option<T>
+{
+    s.split(|c| *c == b',').map(|part| item(trim(part))).collect()
+}
+
+/// Parses a duration like `1:02:03`, `2:03` or `0:00:09.5`.
+fn parse_duration(s: &[u8]) -> Option<Duration> {
+    let (whole, frac) = match memchr::memchr(b'.', s) {
+        Some(dot) => (&s[..dot], &s[dot + 1..]),
+        None => (s, &b""[..]),
+    };
+
+    let mut secs = 0u64;
+    let mut parts = 0;
+    for part in whole.split(|c| *c == b':') {
+        secs = secs.checked_mul(60)?.checked_add(btou::<u64>(part).ok()?)?;
+        parts += 1;
+    }
+    if parts > 3 {
+        return None;
+    }
+
+    let mut nanos = 0u32;
+    if !frac.is_empty() {
+        if frac.len() > 9 || !frac.iter().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        nanos = btou::<u32>(frac).ok()? * 10u32.pow(9 - frac.len() as u32);
+    }
+
+    Some(Duration::new(secs, nanos))
+}
+
+/// Parses an evaluation like `0.17`, `-1.5`, `#-3` or `0.17,20`.
+fn parse_eval(s: &[u8]) -> Option<Command<'_>> {
+    let (value, depth) = match memchr::memchr(b',', s) {
+        Some(comma) => (&s[..comma], Some(btou(trim(&s[comma + 1..])).ok()?)),
+        None => (s, None),
+    };
+
+    let eval = if let Some(mate) = value.strip_prefix(b"#") {
+        Eval::Mate(btoi(mate).ok()?)
+    } else {
+        let (negative, value) = match value.split_first() {
+            Some((&b'-', rest)) => (true, rest),
+            Some((&b'+', rest)) => (false, rest),
+            _ => (false, value),
+        };
+
+        let (pawns, frac) = match memchr::memchr(b'.', value) {
+            Some(dot) => (&value[..dot], &value[dot + 1..]),
+            None => (value, &b""[..]),
+        };
+        if (pawns.is_empty() && frac.is_empty()) || frac.len() > 2 || !frac.iter().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+
+        let pawns = if pawns.is_empty() { 0 } else { btou::<i32>(pawns).ok()? };
+        let frac = if frac.is_empty() { 0 } else { btou::<i32>(frac).ok()? * if frac.len() == 1 { 10 } else { 1 } };
+        let centipawns = pawns.checked_mul(100)?.checked_add(frac)?;
+        Eval::Centipawns(if negative { -centipawns } else { centipawns })
+    };
+
+    Some(Command::Eval { eval, depth })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commands() {
+        let comment = Comment::parse(b"[%clk 1:02:03] [%emt 0:00:09.25][%eval #-3,24] \
+                                       [%csl Ga4,Rb5][%cal Ge2e4, Bd1h5] [%eval 2.1] [%eval .5] \
+                                       [%clk soon] [%unknown a b]");
+
+        assert_eq!(comment.commands, vec![
+            Command::Clock(Duration::from_secs(3723)),
+            Command::ElapsedMoveTime(Duration::from_millis(9250)),
+            Command::Eval { eval: Eval::Mate(-3), depth: Some(24) },
+            Command::Highlights(vec![
+                Highlight { color: AnnotationColor::Green, square: Square::A4 },
+                Highlight { color: AnnotationColor::Red, square: Square::B5 },
+            ]),
+            Command::Arrows(vec![
+                Arrow { color: AnnotationColor::Green, from: Square::E2, to: Square::E4 },
+                Arrow { color: AnnotationColor::Blue, from: Square::D1, to: Square::H5 },
+            ]),
+            Command::Eval { eval: Eval::Centipawns(210), depth: None },
+            Command::Eval { eval: Eval::Centipawns(50), depth: None },
+            Command::Other { name: b"clk", args: b"soon" },
+            Command::Other { name: b"unknown", args: b"a b" },
+        ]);
+
+        assert!(comment.text.is_empty());
+    }
+
+    #[test]
+    fn test_text() {
+        assert_eq!(Comment::parse(b" plain [text] ").text, Cow::Borrowed(&b"plain [text]"[..]));
+        assert_eq!(Comment::parse(b"a [%clk 0:01] b\n[%emt 0:01]c").text, Cow::<[u8]>::Owned(b"a b c".to_vec()));
+        assert_eq!(Comment::parse(b"unterminated [%clk 0:01").text, Cow::Borrowed(&b"unterminated [%clk 0:01"[..]));
+    }
+}
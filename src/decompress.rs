@@ -0,0 +1,267 @@
+// This file is part of the pgn-reader library.
+// Copyright (C) 2017 Niklas Fiekas <niklas.fiekas@backscattering.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Chain, Cursor, Read};
+
+use flate2::bufread::MultiGzDecoder;
+use bzip2::bufread::MultiBzDecoder;
+use xz2::bufread::XzDecoder;
+
+/// Longest magic number of any supported format.
+const MAGIC_LEN: usize = 6;
+
+/// A compression format, as detected by [`Decompressor`].
+///
+/// [`Decompressor`]: struct.Decompressor.html
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum Compression {
+    /// Plain, uncompressed PGN.
+    None,
+    /// gzip, usually `.pgn.gz`.
+    Gzip,
+    /// bzip2, usually `.pgn.bz2`.
+    Bzip2,
+    /// Zstandard, usually `.pgn.zst`.
+    Zstd,
+    /// xz, usually `.pgn.xz`.
+    Xz,
+}
+
+impl Compression {
+    /// Detects the compression format from the magic number at the start
+    /// of a file.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pgn_reader::Compression;
+    ///
+    /// assert_eq!(Compression::detect(b"BZh91AY&SY"), Compression::Bzip2);
+    /// assert_eq!(Compression::detect(b"[Event \"?\"]"), Compression::None);
+    /// ```
+    pub fn detect(magic: &[u8]) -> Compression {
+        if magic.starts_with(b"\x1f\x8b") {
+            Compression::Gzip
+        } else if magic.starts_with(b"BZh") {
+            Compression::Bzip2
+        } else if magic.starts_with(b"\x28\xb5\x2f\xfd") {
+            Compression::Zstd
+        } else if magic.starts_with(b"\xfd7zXZ\x00") {
+            Compression::Xz
+        } else {
+            Compression::None
+        }
+    }
+}
+
+type Peeked<R> = Chain<Cursor<Vec<u8>>, R>;
+
+enum Inner<R> {
+    Plain(Peeked<R>),
+    Gzip(BufReader<MultiGzDecoder<Peeked<R>>>),
+    Bzip2(BufReader<MultiBzDecoder<Peeked<R>>>),
+    Zstd(BufReader<::zstd::Decoder<'static, Peeked<R>>>),
+    Xz(BufReader<XzDecoder<Peeked<R>>>),
+}
+
+/// Transparently decompresses a stream, if it is compressed.
+///
+/// The compression format is detected from the magic number at the start of
+/// the stream. Concatenated (multi-member) archives are supported. Use it as
+/// the input of a [`BufferedReader`].
+///
+/// Requires the `decompress` feature.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::error::Error;
+/// #
+/// # fn try_main() -> Result<(), Box<dyn Error>> {
+/// use std::fs::File;
+/// use std::io::BufReader;
+///
+/// use pgn_reader::{BufferedReader, Decompressor, Visitor};
+///
+/// struct GameCounter;
+///
+/// impl<'pgn> Visitor<'pgn> for GameCounter {
+///     type Result = ();
+///
+///     fn end_game(&mut self, _game: &'pgn [u8]) { }
+/// }
+///
+/// let file = File::open("lichess_db_standard_rated_2014-07.pgn.bz2")?;
+/// let pgn = Decompressor::new(BufReader::new(file))?;
+///
+/// let mut counter = GameCounter;
+/// let games = BufferedReader::new(&mut counter, pgn).into_iter().count();
+/// #
+/// #     Ok(())
+/// # }
+/// #
+/// # fn main() {
+/// #     try_main().unwrap();
+/// # }
+/// ```
+///
+/// [`BufferedReader`]: struct.BufferedReader.html
+pub struct Decompressor<R> {
+    inner: Inner<R>,
+}
+
+impl<R> fmt::Debug for Decompressor<R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Decompressor")
+            .field("compression", &self.compression())
+            .finish()
+    }
+}
+
+impl<R: BufRead> Decompressor<R> {
+    /// Detects the compression format of a stream and prepares to
+    /// decompress it.
+    ///
+    /// # Errors
+    ///
+    /// Returns any I/O error that occurs while reading the magic number.
+    pub fn new(mut inner: R) -> io::Result<Decompressor<R>> {
+        let mut magic = vec![0; MAGIC_LEN];
+        let mut len = 0;
+        while len < MAGIC_LEN {
+            match inner.read(&mut magic[len..]) {
+                Ok(0) => break,
+                Ok(n) => len += n,
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            }
+        }
+        magic.truncate(len);
+
+        let compression = Compression::detect(&magic);
+        let peeked = Cursor::new(magic).chain(inner);
+
+        let inner = match compression {
+            Compression::None => Inner::Plain(peeked),
+            Compression::Gzip => Inner::Gzip(BufReader::new(MultiGzDecoder::new(peeked))),
+            Compression::Bzip2 => Inner::Bzip2(BufReader::new(MultiBzDecoder::new(peeked))),
+            Compression::Zstd => Inner::Zstd(BufReader::new(::zstd::Decoder::with_buffer(peeked)?)),
+            Compression::Xz => Inner::Xz(BufReader::new(XzDecoder::new_multi_decoder(peeked))),
+        };
+
+        Ok(Decompressor { inner })
+    }
+}
+
+impl<R> Decompressor<R> {
+    /// Gets the detected compression format.
+    pub fn compression(&self) -> Compression {
+        match self.inner {
+            Inner::Plain(_) => Compression::None,
+            Inner::Gzip(_) => Compression::Gzip,
+            Inner::Bzip2(_) => Compression::Bzip2,
+            Inner::Zstd(_) => Compression::Zstd,
+            Inner::Xz(_) => Compression::Xz,
+        }
+    }
+}
+
+impl<R: BufRead> Read for Decompressor<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.inner {
+            Inner::Plain(ref mut r) => r.read(buf),
+            Inner::Gzip(ref mut r) => r.read(buf),
+            Inner::Bzip2(ref mut r) => r.read(buf),
+            Inner::Zstd(ref mut r) => r.read(buf),
+            Inner::Xz(ref mut r) => r.read(buf),
+        }
+    }
+}
+
+impl<R: BufRead> BufRead for Decompressor<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        match self.inner {
+            Inner::Plain(ref mut r) => r.fill_buf(),
+            Inner::Gzip(ref mut r) => r.fill_buf(),
+            Inner::Bzip2(ref mut r) => r.fill_buf(),
+            Inner::Zstd(ref mut r) => r.fill_buf(),
+            Inner::Xz(ref mut r) => r.fill_buf(),
+        }
+    }
+
+    fn consume(&mut self, amt: usize) {
+        match self.inner {
+            Inner::Plain(ref mut r) => r.consume(amt),
+            Inner::Gzip(ref mut r) => r.consume(amt),
+            Inner::Bzip2(ref mut r) => r.consume(amt),
+            Inner::Zstd(ref mut r) => r.consume(amt),
+            Inner::Xz(ref mut r) => r.consume(amt),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    const PGN: &[u8] = b"[Event \"Test\"]\n\n1. e4 e5 2. Nf3 *\n";
+
+    fn roundtrip(compressed: &[u8], compression: Compression) {
+        let mut decompressor = Decompressor::new(compressed).expect("magic");
+        assert_eq!(decompressor.compression(), compression);
+
+        let mut pgn = Vec::new();
+        decompressor.read_to_end(&mut pgn).expect("decompress");
+        assert_eq!(pgn, PGN);
+    }
+
+    #[test]
+    fn test_plain() {
+        roundtrip(PGN, Compression::None);
+
+        let mut empty = Decompressor::new(&b""[..]).expect("magic");
+        assert_eq!(empty.compression(), Compression::None);
+        assert_eq!(empty.fill_buf().expect("read"), b"");
+    }
+
+    #[test]
+    fn test_gzip() {
+        let mut encoder = ::flate2::write::GzEncoder::new(Vec::new(), ::flate2::Compression::default());
+        encoder.write_all(PGN).expect("compress");
+        roundtrip(&encoder.finish().expect("compress"), Compression::Gzip);
+    }
+
+    #[test]
+    fn test_bzip2() {
+        let mut encoder = ::bzip2::write::BzEncoder::new(Vec::new(), ::bzip2::Compression::default());
+        encoder.write_all(PGN).expect("compress");
+        roundtrip(&encoder.finish().expect("compress"), Compression::Bzip2);
+    }
+
+    #[test]
+    fn test_zstd() {
+        roundtrip(&::zstd::encode_all(PGN, 0).expect("compress"), Compression::Zstd);
+    }
+
+    #[test]
+    fn test_xz() {
+        let mut encoder = ::xz2::write::XzEncoder::new(Vec::new(), 6);
+        encoder.write_all(PGN).expect("compress");
+        roundtrip(&encoder.finish().expect("compress"), Compression::Xz);
+    }
+}
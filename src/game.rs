@@ -0,0 +1,648 @@
+// This file is part of the pgn-reader library.
+// Copyright (C) 2017 Niklas Fiekas <niklas.fiekas@backscattering.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt;
+use std::iter;
+use std::mem;
+
+use {Visitor, Skip, RawHeader, SanPlus, Uci, Nag, Outcome};
+
+/// A move as written in the movetext.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum PgnMove {
+    /// A move in standard or long algebraic notation, like `Nf3+` or
+    /// `Ng1-f3`.
+    San(SanPlus),
+    /// A move in coordinate notation, like `g1f3`.
+    Uci(Uci),
+}
+
+impl fmt::Display for PgnMove {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PgnMove::San(ref san_plus) => san_plus.fmt(f),
+            PgnMove::Uci(ref uci) => uci.fmt(f),
+        }
+    }
+}
+
+/// A move with its annotations and the alternatives to it.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Node {
+    pub mv: PgnMove,
+    /// Comments before the move, if it is the first move of a variation,
+    /// excluding the braces.
+    pub starting_comments: Vec<Vec<u8>>,
+    /// Numeric annotation glyphs following the move.
+    pub nags: Vec<Nag>,
+    /// Comments following the move, excluding the braces.
+    pub comments: Vec<Vec<u8>>,
+    /// Variations that replace this move, in the order they appear.
+    pub variations: Vec<Line>,
+}
+
+impl Node {
+    /// Creates a node without annotations or variations.
+    pub fn new(mv: PgnMove) -> Node {
+        Node { mv, starting_comments: Vec::new(), nags: Vec::new(), comments: Vec::new(), variations: Vec::new() }
+    }
+}
+
+/// A sequence of moves: the mainline or a variation.
+#[derive(Clone, Default, Eq, PartialEq, Debug)]
+pub struct Line {
+    pub nodes: Vec<Node>,
+}
+
+/// An owned game, as built by a [`GameBuilder`].
+///
+/// [`GameBuilder`]: struct.GameBuilder.html
+#[derive(Clone, Default, Eq, PartialEq, Debug)]
+pub struct Game {
+    /// Headers in the order they appear, with unescaped values.
+    pub headers: Vec<(Vec<u8>, Vec<u8>)>,
+    /// Comments before the first move of the mainline, excluding the braces.
+    pub comments: Vec<Vec<u8>>,
+    pub mainline: Line,
+    /// The game termination marker, or `None` if there is none. `*` is
+    /// `Some(None)`.
+    pub outcome: Option<Option<Outcome>>,
+}
+
+impl Game {
+    /// Gets the value of the first header with the given key.
+    pub fn header(&self, key: &[u8]) -> Option<&[u8]> {
+        self.headers.iter().find(|(k, _)| k == key).map(|(_, v)| &v[..])
+    }
+
+    /// Gets a cursor at the start of the game, before the first move.
+    pub fn cursor(&self) -> Cursor<'_> {
+        Cursor { game: self, path: NodePath::root() }
+    }
+
+    /// Gets the node at `path`.
+    pub fn node(&self, path: &NodePath) -> Option<&Node> {
+        let (line, index) = self.line(path)?;
+        line.nodes.get(index)
+    }
+
+    /// Gets the node at `path` for editing its annotations.
+    pub fn node_mut(&mut self, path: &NodePath) -> Option<&mut Node> {
+        let (line, index) = self.line_mut(path)?;
+        line.nodes.get_mut(index)
+    }
+
+    /// Adds a move after the node at `parent` (or at the start of the game,
+    /// if `parent` is the root). The move continues the line if there is no
+    /// following move yet, and otherwise starts a new variation. Returns the
+    /// path of the new node, or of an existing node with the same move.
+    pub fn add_move(&mut self, parent: &NodePath, mv: PgnMove) -> Option<NodePath> {
+        let next = parent.next();
+        if !parent.is_root() {
+            self.node(parent)?;
+        }
+
+        let (line, index) = self.line_mut(&next)?;
+        let node = match line.nodes.get_mut(index) {
+            Some(node) => node,
+            None => {
+                line.nodes.push(Node::new(mv));
+                return Some(next);
+            },
+        };
+
+        if node.mv == mv {
+            return Some(next);
+        }
+        let v = match node.variations.iter().position(|v| v.nodes.first().is_some_and(|n| n.mv == mv)) {
+            Some(v) => v,
+            None => {
+                node.variations.push(Line { nodes: vec![Node::new(mv)] });
+                node.variations.len() - 1
+            },
+        };
+        Some(next.variation(v))
+    }
+
+    /// Swaps the variation that contains the node at `path` with the line it
+    /// branches off from, so that it becomes the main continuation one level
+    /// up. Returns the new path of the node.
+    pub fn promote(&mut self, path: &NodePath) -> Option<NodePath> {
+        let (anchor, v, j) = match path.split() {
+            Some(split) => split,
+            None => return self.node(path).map(|_| path.clone()),
+        };
+        self.node(path)?;
+
+        let (line, a) = self.line_mut(&anchor)?;
+        let mut tail = line.nodes.split_off(a);
+        let mut variations = mem::take(&mut tail[0].variations);
+        let mut promoted = mem::take(&mut variations[v]);
+        let mut rest = mem::take(&mut promoted.nodes);
+
+        // Starting comments stay with their moves. All alternatives move to
+        // the new main continuation.
+        variations[v] = Line { nodes: tail };
+        variations.append(&mut rest[0].variations);
+        rest[0].variations = variations;
+        line.nodes.append(&mut rest);
+
+        Some(anchor.with_last(a + j))
+    }
+
+    /// Promotes the variation that contains the node at `path` repeatedly,
+    /// until the node is in the mainline. Returns the new path of the node.
+    pub fn promote_to_main(&mut self, path: &NodePath) -> Option<NodePath> {
+        let mut path = path.clone();
+        while !path.is_mainline() {
+            path = self.promote(&path)?;
+        }
+        self.node(&path).map(|_| path)
+    }
+
+    /// Removes the variation that contains the node at `path`. The mainline
+    /// can not be removed.
+    pub fn remove_variation(&mut self, path: &NodePath) -> Option<Line> {
+        let (anchor, v, _) = path.split()?;
+        self.node(path)?;
+        Some(self.node_mut(&anchor)?.variations.remove(v))
+    }
+
+    /// Removes the node at `path` and all following moves of its line,
+    /// including their variations.
+    pub fn truncate(&mut self, path: &NodePath) -> Option<Vec<Node>> {
+        let (line, index) = self.line_mut(path)?;
+        if index < line.nodes.len() {
+            Some(line.nodes.split_off(index))
+        } else {
+            None
+        }
+    }
+
+    /// Finds the line that contains `path` and the index of the node in it.
+    fn line(&self, path: &NodePath) -> Option<(&Line, usize)> {
+        let (&first, rest) = path.0.split_first()?;
+        let mut line = &self.mainline;
+        let mut index = first;
+        for step in rest.chunks(2) {
+            line = line.nodes.get(index)?.variations.get(step[0])?;
+            index = step[1];
+        }
+        Some((line, index))
+    }
+
+    fn line_mut(&mut self, path: &NodePath) -> Option<(&mut Line, usize)> {
+        let (&first, rest) = path.0.split_first()?;
+        let mut line = &mut self.mainline;
+        let mut index = first;
+        for step in rest.chunks(2) {
+            line = line.nodes.get_mut(index)?.variations.get_mut(step[0])?;
+            index = step[1];
+        }
+        Some((line, index))
+    }
+}
+
+/// The location of a node in a [`Game`], or the start of the game (the
+/// root).
+///
+/// Consists of the index in the mainline, followed by pairs of the index of
+/// a variation of that node and the index in the variation.
+///
+/// [`Game`]: struct.Game.html
+#[derive(Clone, Default, Eq, PartialEq, Hash, Debug)]
+pub struct NodePath(Vec<usize>);
+
+impl NodePath {
+    /// The start of the game, before the first move.
+    pub fn root() -> NodePath {
+        NodePath(Vec::new())
+    }
+
+    /// Tests if this is the start of the game.
+    pub fn is_root(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Tests if this is a node in the mainline (or the root).
+    pub fn is_mainline(&self) -> bool {
+        self.0.len() <= 1
+    }
+
+    /// The following node in the same line.
+    fn next(&self) -> NodePath {
+        match self.0.last() {
+            Some(&last) => self.with_last(last + 1),
+            None => NodePath(vec![0]),
+        }
+    }
+
+    /// The first node of a variation of this node.
+    fn variation(&self, v: usize) -> NodePath {
+        let mut path = self.0.clone();
+        path.push(v);
+        path.push(0);
+        NodePath(path)
+    }
+
+    fn with_last(&self, index: usize) -> NodePath {
+        let mut path = self.0.clone();
+        if let Some(last) = path.last_mut() {
+            *last = index;
+        }
+        NodePath(path)
+    }
+
+    /// Splits a path in a variation into the path of the node that the
+    /// variation replaces, the index of the variation and the index in it.
+    fn split(&self) -> Option<(NodePath, usize, usize)> {
+        if self.0.len() < 3 {
+            return None;
+        }
+        let n = self.0.len();
+        Some((NodePath(self.0[..n - 2].to_vec()), self.0[n - 2], self.0[n - 1]))
+    }
+}
+
+/// A position in the move tree of a [`Game`].
+///
+/// The children of a node are the following move in its line and the first
+/// moves of the variations that replace that following move. The root is
+/// the start of the game.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{Reader, GameBuilder};
+///
+/// let pgn = b"1. e4 e5 (1... c5 2. Nf3) 2. Nf3 *";
+///
+/// let mut builder = GameBuilder::new();
+/// let mut game = Reader::new(&mut builder, pgn).read_game().unwrap();
+///
+/// let e4 = game.cursor().next().unwrap();
+/// assert_eq!(e4.children().len(), 2);
+///
+/// // Promote the Sicilian and annotate it.
+/// let c5 = e4.children()[1].path().clone();
+/// let c5 = game.promote(&c5).unwrap();
+/// game.node_mut(&c5).unwrap().comments.push(b"sharper".to_vec());
+///
+/// let mainline: Vec<_> = game.cursor().mainline().map(|c| c.node().unwrap().mv.to_string()).collect();
+/// assert_eq!(mainline, vec!["e4", "c5", "Nf3"]);
+/// ```
+///
+/// [`Game`]: struct.Game.html
+#[derive(Clone, Debug)]
+pub struct Cursor<'a> {
+    game: &'a Game,
+    path: NodePath,
+}
+
+impl<'a> Cursor<'a> {
+    /// Gets the path of the node, for editing the game.
+    pub fn path(&self) -> &NodePath {
+        &self.path
+    }
+
+    /// Gets the node, or `None` at the root.
+    pub fn node(&self) -> Option<&'a Node> {
+        self.game.node(&self.path)
+    }
+
+    /// Moves to the previous move, or to the root.
+    pub fn parent(&self) -> Option<Cursor<'a>> {
+        let path = match self.path.split() {
+            Some((anchor, _, 0)) => return self.at(anchor).parent(),
+            Some(_) | None => match self.path.0.last() {
+                Some(&0) if self.path.is_mainline() => NodePath::root(),
+                Some(&last) => self.path.with_last(last - 1),
+                None => return None,
+            },
+        };
+        Some(self.at(path))
+    }
+
+    /// Gets all children, the main continuation first.
+    pub fn children(&self) -> Vec<Cursor<'a>> {
+        let next = self.path.next();
+        match self.game.node(&next) {
+            Some(node) => {
+                let variations = node.variations.iter().enumerate()
+                    .filter(|&(_, line)| !line.nodes.is_empty())
+                    .map(|(v, _)| self.at(next.variation(v)));
+                Some(self.at(next.clone())).into_iter().chain(variations).collect()
+            },
+            None => Vec::new(),
+        }
+    }
+
+    /// Moves to the main continuation.
+    pub fn next(&self) -> Option<Cursor<'a>> {
+        let next = self.path.next();
+        self.game.node(&next).map(|_| self.at(next))
+    }
+
+    /// Moves to the next alternative to this move.
+    pub fn next_sibling(&self) -> Option<Cursor<'a>> {
+        let siblings = self.parent()?.children();
+        let index = siblings.iter().position(|c| c.path == self.path)?;
+        siblings.into_iter().nth(index + 1)
+    }
+
+    /// Moves to the previous alternative to this move.
+    pub fn prev_sibling(&self) -> Option<Cursor<'a>> {
+        let siblings = self.parent()?.children();
+        let index = siblings.iter().position(|c| c.path == self.path)?;
+        siblings.into_iter().nth(index.checked_sub(1)?)
+    }
+
+    /// Iterates over the main continuation, starting after this node.
+    pub fn mainline(&self) -> impl Iterator<Item=Cursor<'a>> {
+        iter::successors(self.next(), Cursor::next)
+    }
+
+    fn at(&self, path: NodePath) -> Cursor<'a> {
+        Cursor { game: self.game, path }
+    }
+}
+
+/// A visitor that collects each game into a [`Game`].
+///
+/// Variations that do not follow a move are skipped. Syntax errors are
+/// ignored.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{Reader, GameBuilder};
+///
+/// let pgn = b"[White \"Morphy\"]\n\n1. e4 { best } (1. d4 d5) e5 $1 *";
+///
+/// let mut builder = GameBuilder::new();
+/// let game = Reader::new(&mut builder, pgn).read_game().unwrap();
+///
+/// assert_eq!(game.header(b"White"), Some(&b"Morphy"[..]));
+/// assert_eq!(game.mainline.nodes.len(), 2);
+/// assert_eq!(game.mainline.nodes[0].comments, vec![b" best ".to_vec()]);
+/// assert_eq!(game.mainline.nodes[0].variations[0].nodes.len(), 2);
+/// ```
+///
+/// [`Game`]: struct.Game.html
+#[derive(Default, Debug)]
+pub struct GameBuilder {
+    game: Game,
+    /// Variations that are still open, innermost last.
+    stack: Vec<Line>,
+    /// Comments at the start of the innermost variation, until its first
+    /// move.
+    pending: Vec<Vec<u8>>,
+}
+
+impl GameBuilder {
+    /// Creates a new game builder.
+    pub fn new() -> GameBuilder {
+        GameBuilder::default()
+    }
+
+    fn line(&mut self) -> &mut Line {
+        match self.stack.last_mut() {
+            Some(line) => line,
+            None => &mut self.game.mainline,
+        }
+    }
+
+    fn push(&mut self, mv: PgnMove) {
+        let mut node = Node::new(mv);
+        node.starting_comments = mem::take(&mut self.pending);
+        self.line().nodes.push(node);
+    }
+
+    fn close_variation(&mut self) {
+        // Drop comments of an empty variation.
+        self.pending.clear();
+        if let Some(variation) = self.stack.pop() {
+            if let Some(node) = self.line().nodes.last_mut() {
+                node.variations.push(variation);
+            }
+        }
+    }
+}
+
+impl<'pgn> Visitor<'pgn> for GameBuilder {
+    type Result = Game;
+
+    fn begin_game(&mut self) {
+        self.game = Game::default();
+        self.stack.clear();
+        self.pending.clear();
+    }
+
+    fn header(&mut self, key: &'pgn [u8], value: RawHeader<'pgn>) {
+        self.game.headers.push((key.to_vec(), value.decode().into_owned()));
+    }
+
+    fn san(&mut self, san_plus: SanPlus) {
+        self.push(PgnMove::San(san_plus));
+    }
+
+    fn uci(&mut self, uci: Uci) {
+        self.push(PgnMove::Uci(uci));
+    }
+
+    fn nag(&mut self, nag: Nag) {
+        if let Some(node) = self.line().nodes.last_mut() {
+            node.nags.push(nag);
+        }
+    }
+
+    fn comment(&mut self, comment: &'pgn [u8]) {
+        let comment = comment.to_vec();
+        let in_variation = !self.stack.is_empty();
+        match self.line().nodes.last_mut() {
+            Some(node) => node.comments.push(comment),
+            None if in_variation => self.pending.push(comment),
+            None => self.game.comments.push(comment),
+        }
+    }
+
+    fn begin_variation(&mut self) -> Skip {
+        // Skip variations that have no move to replace. Still push an empty
+        // line, so that it is popped in end_variation.
+        let skip = self.line().nodes.is_empty();
+        self.stack.push(Line::default());
+        Skip(skip)
+    }
+
+    fn end_variation(&mut self) {
+        self.close_variation();
+    }
+
+    fn outcome(&mut self, outcome: Option<Outcome>) {
+        self.game.outcome = Some(outcome);
+    }
+
+    fn end_game(&mut self, _game: &'pgn [u8]) -> Game {
+        while !self.stack.is_empty() {
+            self.close_variation();
+        }
+        mem::take(&mut self.game)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {Reader, San};
+
+    fn san(s: &str) -> PgnMove {
+        PgnMove::San(s.parse().expect("valid san"))
+    }
+
+    #[test]
+    fn test_variations() {
+        let pgn = b"[Event \"?\"]\n[Site \"The \\\"Club\\\"\"]\n\n\
+                    { start } 1. e4 e5 (1... c5 2. Nf3 (2. c3 $2) { open } 2... d6) \
+                    (1... e6) 2. g1f3 $5 ( { unclosed } 2. Nc3";
+
+        let mut builder = GameBuilder::new();
+        let game = Reader::new(&mut builder, pgn).read_game().expect("game");
+
+        assert_eq!(game.header(b"Site"), Some(&b"The \"Club\""[..]));
+        assert_eq!(game.outcome, None);
+        assert_eq!(game.comments, vec![b" start ".to_vec()]);
+
+        let nodes = &game.mainline.nodes;
+        assert_eq!(nodes.iter().map(|n| n.mv.clone()).collect::<Vec<_>>(), vec![
+            san("e4"), san("e5"), PgnMove::Uci("g1f3".parse().expect("valid uci")),
+        ]);
+
+        assert_eq!(nodes[1].variations.len(), 2);
+        let sicilian = &nodes[1].variations[0];
+        assert_eq!(sicilian.nodes.len(), 3);
+        assert_eq!(sicilian.nodes[1].comments, vec![b" open ".to_vec()]);
+        assert_eq!(sicilian.nodes[1].variations[0].nodes[0].mv, san("c3"));
+        assert_eq!(sicilian.nodes[1].variations[0].nodes[0].nags, vec![Nag(2)]);
+        assert_eq!(nodes[1].variations[1].nodes[0].mv, san("e6"));
+
+        assert_eq!(nodes[2].nags, vec![Nag(5)]);
+        assert_eq!(nodes[2].variations[0].nodes[0].starting_comments, vec![b" unclosed ".to_vec()]);
+        assert_eq!(nodes[2].variations[0].nodes[0].mv, san("Nc3"));
+    }
+
+    #[test]
+    fn test_reuse() {
+        let pgn = b"1. e4 ( ( 1. d4 ) ) 1-0\n\n1. -- *";
+
+        let mut builder = GameBuilder::new();
+        let games: Vec<_> = Reader::new(&mut builder, pgn).into_iter().collect();
+
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].outcome, Some(Some(Outcome::Decisive { winner: ::Color::White })));
+        assert_eq!(games[0].mainline.nodes[0].variations.len(), 1);
+        assert!(games[0].mainline.nodes[0].variations[0].nodes.is_empty());
+        assert_eq!(games[1].mainline.nodes, vec![Node::new(PgnMove::San(SanPlus { san: San::Null, check: false, checkmate: false }))]);
+        assert_eq!(games[1].outcome, Some(None));
+    }
+
+    fn game(pgn: &[u8]) -> Game {
+        let mut builder = GameBuilder::new();
+        Reader::new(&mut builder, pgn).read_game().expect("game")
+    }
+
+    fn moves<'a, I: Iterator<Item=Cursor<'a>>>(cursors: I) -> Vec<String> {
+        cursors.map(|c| c.node().expect("node").mv.to_string()).collect()
+    }
+
+    #[test]
+    fn test_cursor() {
+        let game = game(b"1. e4 e5 (1... c5 2. Nf3 (2. c3) d6) (1... e6) 2. Nf3 *");
+
+        let root = game.cursor();
+        assert!(root.node().is_none());
+        assert!(root.parent().is_none());
+        assert_eq!(moves(root.mainline()), vec!["e4", "e5", "Nf3"]);
+
+        let e4 = root.next().expect("e4");
+        assert_eq!(e4.parent().expect("root").path(), &NodePath::root());
+        assert_eq!(moves(e4.children().into_iter()), vec!["e5", "c5", "e6"]);
+
+        let c5 = e4.children()[1].clone();
+        assert_eq!(moves(c5.mainline()), vec!["Nf3", "d6"]);
+        assert_eq!(c5.parent().expect("e4").path(), e4.path());
+        assert_eq!(moves(c5.next_sibling().into_iter()), vec!["e6"]);
+        assert_eq!(moves(c5.prev_sibling().into_iter()), vec!["e5"]);
+        assert!(c5.next_sibling().expect("e6").next_sibling().is_none());
+
+        let c3 = c5.children()[1].clone();
+        assert_eq!(c3.node().expect("c3").mv.to_string(), "c3");
+        assert_eq!(moves(c3.parent().into_iter()), vec!["c5"]);
+        assert!(c3.next().is_none());
+    }
+
+    #[test]
+    fn test_editing() {
+        let mut game = game(b"1. e4 e5 (1... c5 2. Nf3 (2. c3) d6) (1... e6) 2. Nf3 *");
+
+        // Promote 2. c3 to the mainline.
+        let c3 = game.cursor().next().expect("e4").children()[1].children()[1].path().clone();
+        let c3 = game.promote_to_main(&c3).expect("promote");
+        assert_eq!(c3, NodePath(vec![2]));
+        assert_eq!(moves(game.cursor().mainline()), vec!["e4", "c5", "c3"]);
+
+        let c5 = game.cursor().next().expect("e4").next().expect("c5");
+        assert_eq!(moves(c5.children().into_iter()), vec!["c3", "Nf3"]);
+        assert_eq!(moves(c5.next_sibling().into_iter()), vec!["e5"]);
+        assert_eq!(moves(c5.next_sibling().expect("e5").next_sibling().into_iter()), vec!["e6"]);
+        let e5 = c5.next_sibling().expect("e5").path().clone();
+        assert_eq!(moves(game.cursor().next().expect("e4").children()[1].mainline()), vec!["Nf3"]);
+
+        // Remove 1... e5 and truncate after 1... c5.
+        let removed = game.remove_variation(&e5).expect("removed");
+        assert_eq!(removed.nodes.len(), 2);
+        let c3 = game.truncate(&NodePath(vec![2])).expect("truncated");
+        assert_eq!(c3.len(), 1);
+        assert_eq!(game.remove_variation(&NodePath(vec![1])), None);
+
+        // Add moves and a comment.
+        let nc6 = game.add_move(&NodePath(vec![1]), "Nc6".parse().map(PgnMove::San).expect("Nc6"));
+        assert_eq!(nc6, Some(NodePath(vec![2])));
+        let d4 = game.add_move(&NodePath(vec![1]), "d4".parse().map(PgnMove::San).expect("d4")).expect("d4");
+        assert_eq!(d4, NodePath(vec![2, 0, 0]));
+        assert_eq!(game.add_move(&NodePath(vec![1]), "d4".parse().map(PgnMove::San).expect("d4")), Some(d4.clone()));
+        game.node_mut(&d4).expect("d4").comments.push(b"open".to_vec());
+        assert_eq!(game.node(&d4).expect("d4").comments, vec![b"open".to_vec()]);
+
+        let d3 = game.add_move(&NodePath::root(), "d3".parse().map(PgnMove::San).expect("d3"));
+        assert_eq!(d3, Some(NodePath(vec![0, 0, 0])));
+        assert_eq!(game.add_move(&NodePath(vec![9]), "d3".parse().map(PgnMove::San).expect("d3")), None);
+    }
+
+    #[test]
+    fn test_promote_comments() {
+        let mut game = game(b"1. e4 e5 ( { better is } 1... c5 { sicilian } ) *");
+        let c5 = game.promote(&NodePath(vec![1, 0, 0])).expect("promote");
+        assert_eq!(c5, NodePath(vec![1]));
+
+        let node = game.node(&c5).expect("c5");
+        assert_eq!(node.starting_comments, vec![b" better is ".to_vec()]);
+        assert_eq!(node.comments, vec![b" sicilian ".to_vec()]);
+        assert_eq!(node.variations.len(), 1);
+        assert!(node.variations[0].nodes[0].starting_comments.is_empty());
+        let c5 = game.cursor().next().expect("e4").next().expect("c5");
+        assert_eq!(moves(c5.next_sibling().into_iter()), vec!["e5"]);
+    }
+}
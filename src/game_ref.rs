@@ -0,0 +1,454 @@
+// This file is part of the pgn-reader library.
+// Copyright (C) 2017 Niklas Fiekas <niklas.fiekas@backscattering.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt;
+
+use {Visitor, Skip, RawHeader, SanPlus, Uci, Nag, Outcome};
+use game::{Game, Line, Node, PgnMove};
+
+/// Marks a missing link between nodes.
+const NONE: u32 = u32::MAX;
+
+/// Annotation key of comments before the first move of the mainline.
+const ROOT: usize = usize::MAX;
+
+/// Annotation key of comments before the first move of a variation, while
+/// that move is not yet known.
+const PENDING: usize = usize::MAX - 1;
+
+fn before(node: u32) -> usize {
+    node as usize * 2
+}
+
+fn after(node: u32) -> usize {
+    node as usize * 2 + 1
+}
+
+#[derive(Clone, Debug)]
+struct NodeData {
+    mv: PgnMove,
+    next: u32,
+    first_variation: u32,
+    next_sibling: u32,
+}
+
+/// A game that borrows headers and comments from the input.
+///
+/// Moves are stored in a flat arena, and annotations in flat lists sorted
+/// by node. Navigate the move tree starting from
+/// [`mainline()`](#method.mainline). Built by a [`GameRefBuilder`].
+///
+/// [`GameRefBuilder`]: struct.GameRefBuilder.html
+#[derive(Clone, Default, Debug)]
+pub struct GameRef<'pgn> {
+    headers: Vec<(&'pgn [u8], RawHeader<'pgn>)>,
+    nodes: Vec<NodeData>,
+    comments: Vec<(usize, &'pgn [u8])>,
+    nags: Vec<(usize, Nag)>,
+    outcome: Option<Option<Outcome>>,
+}
+
+impl<'pgn> GameRef<'pgn> {
+    /// Gets the headers in the order they appear.
+    pub fn headers(&self) -> &[(&'pgn [u8], RawHeader<'pgn>)] {
+        &self.headers
+    }
+
+    /// Gets the value of the first header with the given key.
+    pub fn header(&self, key: &[u8]) -> Option<RawHeader<'pgn>> {
+        self.headers.iter().find(|&&(k, _)| k == key).map(|&(_, v)| v)
+    }
+
+    /// Gets the comments before the first move of the mainline.
+    pub fn comments<'a>(&'a self) -> impl Iterator<Item=&'pgn [u8]> + 'a {
+        self.comments_at(ROOT)
+    }
+
+    /// Gets the first move of the mainline.
+    pub fn mainline(&self) -> Option<NodeRef<'_, 'pgn>> {
+        self.node(0)
+    }
+
+    /// Gets the total number of moves in the mainline and all variations.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Tests if the game has no moves.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Gets the game termination marker, or `None` if there is none. `*` is
+    /// `Some(None)`.
+    pub fn outcome(&self) -> Option<Option<&Outcome>> {
+        self.outcome.as_ref().map(Option::as_ref)
+    }
+
+    /// Copies the game into an owned [`Game`].
+    ///
+    /// [`Game`]: struct.Game.html
+    pub fn to_game(&self) -> Game {
+        Game {
+            headers: self.headers.iter().map(|&(k, v)| (k.to_vec(), v.decode().into_owned())).collect(),
+            comments: self.comments().map(<[u8]>::to_vec).collect(),
+            mainline: Line {
+                nodes: self.mainline().map_or_else(Vec::new, |node| node.to_nodes()),
+            },
+            outcome: self.outcome.clone(),
+        }
+    }
+
+    fn node(&self, index: u32) -> Option<NodeRef<'_, 'pgn>> {
+        if (index as usize) < self.nodes.len() {
+            Some(NodeRef { game: self, index })
+        } else {
+            None
+        }
+    }
+
+    fn comments_at<'a>(&'a self, key: usize) -> impl Iterator<Item=&'pgn [u8]> + 'a {
+        let start = self.comments.partition_point(|&(k, _)| k < key);
+        let end = self.comments.partition_point(|&(k, _)| k <= key);
+        self.comments[start..end].iter().map(|&(_, comment)| comment)
+    }
+
+    fn clear(&mut self) {
+        self.headers.clear();
+        self.nodes.clear();
+        self.comments.clear();
+        self.nags.clear();
+        self.outcome = None;
+    }
+}
+
+/// A move in a [`GameRef`].
+///
+/// [`GameRef`]: struct.GameRef.html
+#[derive(Copy, Clone)]
+pub struct NodeRef<'a, 'pgn: 'a> {
+    game: &'a GameRef<'pgn>,
+    index: u32,
+}
+
+impl<'a, 'pgn> NodeRef<'a, 'pgn> {
+    /// Gets the move.
+    pub fn mv(&self) -> &'a PgnMove {
+        &self.data().mv
+    }
+
+    /// Gets the numeric annotation glyphs following the move.
+    pub fn nags(&self) -> impl Iterator<Item=Nag> + 'a {
+        let key = self.index as usize;
+        let nags = &self.game.nags;
+        let start = nags.partition_point(|&(k, _)| k < key);
+        let end = nags.partition_point(|&(k, _)| k <= key);
+        nags[start..end].iter().map(|(_, nag)| nag.clone())
+    }
+
+    /// Gets the comments following the move.
+    pub fn comments(&self) -> impl Iterator<Item=&'pgn [u8]> + 'a {
+        self.game.comments_at(after(self.index))
+    }
+
+    /// Gets the comments before the move, if it is the first move of a
+    /// variation.
+    pub fn starting_comments(&self) -> impl Iterator<Item=&'pgn [u8]> + 'a {
+        self.game.comments_at(before(self.index))
+    }
+
+    /// Gets the following move in the same line.
+    pub fn next(&self) -> Option<NodeRef<'a, 'pgn>> {
+        self.game.node(self.data().next)
+    }
+
+    /// Gets the first moves of the variations that replace this move.
+    pub fn variations(&self) -> Variations<'a, 'pgn> {
+        Variations { game: self.game, next: self.data().first_variation }
+    }
+
+    fn data(&self) -> &'a NodeData {
+        &self.game.nodes[self.index as usize]
+    }
+
+    fn to_nodes(self) -> Vec<Node> {
+        let mut nodes = Vec::new();
+        let mut current = Some(self);
+        while let Some(node) = current {
+            nodes.push(Node {
+                mv: node.mv().clone(),
+                starting_comments: node.starting_comments().map(<[u8]>::to_vec).collect(),
+                nags: node.nags().collect(),
+                comments: node.comments().map(<[u8]>::to_vec).collect(),
+                variations: node.variations().map(|first| Line { nodes: first.to_nodes() }).collect(),
+            });
+            current = node.next();
+        }
+        nodes
+    }
+}
+
+impl<'a, 'pgn> fmt::Debug for NodeRef<'a, 'pgn> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("NodeRef")
+            .field("index", &self.index)
+            .field("mv", self.mv())
+            .finish()
+    }
+}
+
+/// Iterator over the first moves of the variations of a [`NodeRef`].
+///
+/// [`NodeRef`]: struct.NodeRef.html
+#[derive(Clone)]
+pub struct Variations<'a, 'pgn: 'a> {
+    game: &'a GameRef<'pgn>,
+    next: u32,
+}
+
+impl<'a, 'pgn> Iterator for Variations<'a, 'pgn> {
+    type Item = NodeRef<'a, 'pgn>;
+
+    fn next(&mut self) -> Option<NodeRef<'a, 'pgn>> {
+        let node = self.game.node(self.next)?;
+        self.next = node.data().next_sibling;
+        Some(node)
+    }
+}
+
+impl<'a, 'pgn> fmt::Debug for Variations<'a, 'pgn> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Variations").finish()
+    }
+}
+
+/// A line that is being read.
+#[derive(Debug)]
+struct Frame {
+    /// Last move in the line.
+    last: u32,
+    /// Move that the line replaces, or `NONE` for the mainline and skipped
+    /// variations.
+    anchor: u32,
+    /// Position of the first comment before the first move.
+    pending: usize,
+}
+
+/// A reusable visitor that collects each game into a [`GameRef`] and passes
+/// it to a callback. The buffers of the game are recycled for the next
+/// game, so that reading many games allocates very little.
+///
+/// The callback result is returned from the reader. Clone the [`GameRef`]
+/// to keep it. Unlike [`GameBuilder`], variations without moves are
+/// dropped.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{Reader, GameRef, GameRefBuilder};
+///
+/// let pgn = b"[White \"Morphy\"]\n\n1. e4 { best } (1. d4 d5) e5 *\n\n1. d4 *";
+///
+/// let mut builder = GameRefBuilder::new(|game: &GameRef| {
+///     let white = game.header(b"White").map(|v| v.decode_utf8_lossy().into_owned());
+///     let comments = game.mainline().map_or(0, |first| first.comments().count());
+///     (white, comments, game.len())
+/// });
+/// let games: Vec<_> = Reader::new(&mut builder, pgn).into_iter().collect();
+///
+/// assert_eq!(games, vec![(Some("Morphy".to_owned()), 1, 4), (None, 0, 1)]);
+/// ```
+///
+/// [`GameRef`]: struct.GameRef.html
+/// [`GameBuilder`]: struct.GameBuilder.html
+pub struct GameRefBuilder<'pgn, F> {
+    game: GameRef<'pgn>,
+    stack: Vec<Frame>,
+    callback: F,
+}
+
+impl<'pgn, F> fmt::Debug for GameRefBuilder<'pgn, F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("GameRefBuilder").finish()
+    }
+}
+
+impl<'pgn, F> GameRefBuilder<'pgn, F> {
+    /// Creates a builder that passes each game to `callback`.
+    pub fn new(callback: F) -> GameRefBuilder<'pgn, F> {
+        GameRefBuilder { game: GameRef::default(), stack: Vec::new(), callback }
+    }
+
+    fn push(&mut self, mv: PgnMove) {
+        let index = self.game.nodes.len() as u32;
+        self.game.nodes.push(NodeData { mv, next: NONE, first_variation: NONE, next_sibling: NONE });
+
+        let GameRefBuilder { ref mut game, ref mut stack, .. } = *self;
+        let frame = stack.last_mut().expect("mainline frame");
+        if frame.last != NONE {
+            game.nodes[frame.last as usize].next = index;
+        } else if frame.anchor != NONE {
+            // First move of a variation. Append it to the variations of the
+            // anchor.
+            let anchor = &mut game.nodes[frame.anchor as usize];
+            if anchor.first_variation == NONE {
+                anchor.first_variation = index;
+            } else {
+                let mut tail = anchor.first_variation;
+                while game.nodes[tail as usize].next_sibling != NONE {
+                    tail = game.nodes[tail as usize].next_sibling;
+                }
+                game.nodes[tail as usize].next_sibling = index;
+            }
+
+            for comment in &mut game.comments[frame.pending..] {
+                comment.0 = before(index);
+            }
+        }
+        frame.last = index;
+    }
+
+    fn close_variation(&mut self) {
+        if self.stack.len() > 1 {
+            let frame = self.stack.pop().expect("variation frame");
+            if frame.last == NONE {
+                // Drop comments of an empty variation.
+                self.game.comments.truncate(frame.pending);
+            }
+        }
+    }
+
+    fn frame(&self) -> &Frame {
+        self.stack.last().expect("mainline frame")
+    }
+}
+
+impl<'pgn, F, T> Visitor<'pgn> for GameRefBuilder<'pgn, F>
+    where F: FnMut(&GameRef<'pgn>) -> T
+{
+    type Result = T;
+
+    fn begin_game(&mut self) {
+        self.game.clear();
+        self.stack.clear();
+        self.stack.push(Frame { last: NONE, anchor: NONE, pending: 0 });
+    }
+
+    fn header(&mut self, key: &'pgn [u8], value: RawHeader<'pgn>) {
+        self.game.headers.push((key, value));
+    }
+
+    fn san(&mut self, san_plus: SanPlus) {
+        self.push(PgnMove::San(san_plus));
+    }
+
+    fn uci(&mut self, uci: Uci) {
+        self.push(PgnMove::Uci(uci));
+    }
+
+    fn nag(&mut self, nag: Nag) {
+        let last = self.frame().last;
+        if last != NONE {
+            self.game.nags.push((last as usize, nag));
+        }
+    }
+
+    fn comment(&mut self, comment: &'pgn [u8]) {
+        let key = match *self.frame() {
+            Frame { last, .. } if last != NONE => after(last),
+            Frame { anchor: NONE, .. } => ROOT,
+            _ => PENDING,
+        };
+        self.game.comments.push((key, comment));
+    }
+
+    fn begin_variation(&mut self) -> Skip {
+        // Skip variations that have no move to replace.
+        let anchor = self.frame().last;
+        self.stack.push(Frame { last: NONE, anchor, pending: self.game.comments.len() });
+        Skip(anchor == NONE)
+    }
+
+    fn end_variation(&mut self) {
+        self.close_variation();
+    }
+
+    fn outcome(&mut self, outcome: Option<Outcome>) {
+        self.game.outcome = Some(outcome);
+    }
+
+    fn end_game(&mut self, _game: &'pgn [u8]) -> T {
+        while self.stack.len() > 1 {
+            self.close_variation();
+        }
+
+        // Sort annotations by node. The sort is stable, so that annotations
+        // of the same node stay in order.
+        self.game.comments.sort_by_key(|&(key, _)| key);
+        self.game.nags.sort_by_key(|&(key, _)| key);
+
+        (self.callback)(&self.game)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {Reader, GameBuilder};
+
+    #[test]
+    fn test_same_as_owned() {
+        let pgn = b"[Event \"?\"]\n[Site \"The \\\"Club\\\"\"]\n\n\
+                    { start } 1. e4 e5 (1... c5 2. Nf3 (2. c3 $2) { open } 2... d6) \
+                    ({ french } 1... e6 (1... d5 $1 $2) { after }) 2. g1-f3 $5 ( { unclosed } 2. Nc3\n\n\
+                    { only a comment } 1-0\n\n\
+                    1. d4 d5 *";
+
+        let mut builder = GameBuilder::new();
+        let expected: Vec<_> = Reader::new(&mut builder, pgn).into_iter().collect();
+
+        let mut builder = GameRefBuilder::new(|game: &GameRef| game.to_game());
+        let games: Vec<_> = Reader::new(&mut builder, pgn).into_iter().collect();
+
+        assert_eq!(games.len(), 3);
+        assert_eq!(games, expected);
+        assert_eq!(games.iter().map(|g| g.outcome.clone()).collect::<Vec<_>>(), vec![
+            None,
+            Some(Some(Outcome::Decisive { winner: ::Color::White })),
+            Some(None),
+        ]);
+    }
+
+    #[test]
+    fn test_navigation() {
+        let pgn = b"1. e4 (1. d4) (1. c4 (1. Nf3)) e5 $1 $3 *";
+
+        let mut builder = GameRefBuilder::new(|game: &GameRef| {
+            let e4 = game.mainline().expect("e4");
+            let variations: Vec<_> = e4.variations().map(|n| n.mv().clone()).collect();
+            let c4 = e4.variations().nth(1).expect("c4");
+            let e5 = e4.next().expect("e5");
+            (game.len(), variations, c4.variations().count(), e5.nags().collect::<Vec<_>>(), e5.next().is_none())
+        });
+        let result = Reader::new(&mut builder, pgn).read_game().expect("game");
+
+        assert_eq!(result.0, 5);
+        assert_eq!(result.1, vec![PgnMove::San("d4".parse().expect("d4")), PgnMove::San("c4".parse().expect("c4"))]);
+        assert_eq!(result.2, 1);
+        assert_eq!(result.3, vec![Nag(1), Nag(3)]);
+        assert!(result.4);
+    }
+}
@@ -0,0 +1,789 @@
+// This file is part of the pgn-reader library.
+// Copyright (C) 2017 Niklas Fiekas <niklas.fiekas@backscattering.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt;
+use std::str::FromStr;
+use std::error::Error;
+
+use btoi::btou;
+use shakmaty::fen::Fen;
+
+use {Visitor, Skip, RawHeader, Outcome};
+
+/// Error when parsing an invalid header value.
+#[derive(Clone, Eq, PartialEq)]
+pub struct InvalidHeaderValue {
+    _priv: (),
+}
+
+impl fmt::Debug for InvalidHeaderValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("InvalidHeaderValue").finish()
+    }
+}
+
+impl fmt::Display for InvalidHeaderValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        "invalid header value".fmt(f)
+    }
+}
+
+impl Error for InvalidHeaderValue {
+    fn description(&self) -> &str {
+        "invalid header value"
+    }
+}
+
+fn invalid<T>() -> Result<T, InvalidHeaderValue> {
+    Err(InvalidHeaderValue { _priv: () })
+}
+
+/// Parses a number with at most `max_len` digits, or `None` if all digits
+/// are replaced by `?`.
+fn partial(s: &[u8], max_len: usize) -> Result<Option<u16>, InvalidHeaderValue> {
+    if s.is_empty() || s.len() > max_len {
+        invalid()
+    } else if s.iter().all(|c| *c == b'?') {
+        Ok(None)
+    } else if s.iter().all(|c| c.is_ascii_digit()) {
+        btou(s).map(Some).or_else(|_| invalid())
+    } else {
+        invalid()
+    }
+}
+
+/// A date like `2017.05.??`, where any part may be unknown. Used in the
+/// `Date`, `EventDate` and `UTCDate` headers.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::Date;
+///
+/// let date = Date::from_bytes(b"2017.05.??").unwrap();
+/// assert_eq!(date, Date { year: Some(2017), month: Some(5), day: None });
+/// assert_eq!(date.to_string(), "2017.05.??");
+/// ```
+#[derive(Copy, Clone, Default, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct Date {
+    pub year: Option<u16>,
+    pub month: Option<u8>,
+    pub day: Option<u8>,
+}
+
+impl Date {
+    /// Parses a date.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`InvalidHeaderValue`] if the input is not of the form
+    /// `YYYY.MM.DD`, with each part possibly replaced by question marks.
+    ///
+    /// [`InvalidHeaderValue`]: struct.InvalidHeaderValue.html
+    pub fn from_bytes(s: &[u8]) -> Result<Date, InvalidHeaderValue> {
+        let mut parts = s.split(|c| *c == b'.');
+        let (year, month, day) = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(year), Some(month), Some(day), None) => (year, month, day),
+            _ => return invalid(),
+        };
+
+        let date = Date {
+            year: partial(year, 4)?,
+            month: partial(month, 2)?.map(|m| m as u8),
+            day: partial(day, 2)?.map(|d| d as u8),
+        };
+
+        if date.month.is_some_and(|m| !(1..=12).contains(&m)) || date.day.is_some_and(|d| d < 1 || d > date.days_in_month()) {
+            return invalid();
+        }
+
+        Ok(date)
+    }
+
+    /// Gets the number of days in the month, allowing for the longest month
+    /// if the year or month are unknown.
+    fn days_in_month(&self) -> u8 {
+        match self.month {
+            Some(4) | Some(6) | Some(9) | Some(11) => 30,
+            Some(2) => match self.year {
+                Some(year) if year % 4 != 0 || (year % 100 == 0 && year % 400 != 0) => 28,
+                _ => 29,
+            },
+            _ => 31,
+        }
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.year {
+            Some(year) => write!(f, "{:04}", year)?,
+            None => f.write_str("????")?,
+        }
+        for part in &[self.month, self.day] {
+            match *part {
+                Some(part) => write!(f, ".{:02}", part)?,
+                None => f.write_str(".??")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Date {
+    type Err = InvalidHeaderValue;
+
+    fn from_str(s: &str) -> Result<Date, InvalidHeaderValue> {
+        Date::from_bytes(s.as_bytes())
+    }
+}
+
+/// A time of day like `15:04:59`, as in the `UTCTime` header.
+#[derive(Copy, Clone, Default, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct Time {
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl Time {
+    /// Parses a time.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`InvalidHeaderValue`] if the input is not a valid time of
+    /// the form `HH:MM:SS`.
+    ///
+    /// [`InvalidHeaderValue`]: struct.InvalidHeaderValue.html
+    pub fn from_bytes(s: &[u8]) -> Result<Time, InvalidHeaderValue> {
+        let mut parts = s.split(|c| *c == b':');
+        let (hour, minute, second) = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(hour), Some(minute), Some(second), None) => (hour, minute, second),
+            _ => return invalid(),
+        };
+
+        match (partial(hour, 2)?, partial(minute, 2)?, partial(second, 2)?) {
+            (Some(hour), Some(minute), Some(second)) if hour < 24 && minute < 60 && second < 60 =>
+                Ok(Time { hour: hour as u8, minute: minute as u8, second: second as u8 }),
+            _ => invalid(),
+        }
+    }
+}
+
+impl fmt::Display for Time {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:02}:{:02}:{:02}", self.hour, self.minute, self.second)
+    }
+}
+
+impl FromStr for Time {
+    type Err = InvalidHeaderValue;
+
+    fn from_str(s: &str) -> Result<Time, InvalidHeaderValue> {
+        Time::from_bytes(s.as_bytes())
+    }
+}
+
+/// The value of the `Round` header.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::Round;
+///
+/// assert_eq!(Round::from_bytes(b"3.1"), Ok(Round::Number(vec![3, 1])));
+/// assert_eq!(Round::from_bytes(b"?"), Ok(Round::Unknown));
+/// assert_eq!(Round::from_bytes(b"-"), Ok(Round::NotApplicable));
+/// ```
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub enum Round {
+    /// `?`
+    Unknown,
+    /// `-`
+    NotApplicable,
+    /// A round like `3`, or a sub-round like `3.1` for the first game of
+    /// the third round.
+    Number(Vec<u32>),
+}
+
+impl Round {
+    /// Parses a round.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`InvalidHeaderValue`] if the input is neither `?`, `-` nor
+    /// numbers separated by dots.
+    ///
+    /// [`InvalidHeaderValue`]: struct.InvalidHeaderValue.html
+    pub fn from_bytes(s: &[u8]) -> Result<Round, InvalidHeaderValue> {
+        match s {
+            b"?" => Ok(Round::Unknown),
+            b"-" => Ok(Round::NotApplicable),
+            _ => s.split(|c| *c == b'.')
+                .map(|part| if part.iter().all(|c| c.is_ascii_digit()) { btou(part).ok() } else { None })
+                .collect::<Option<Vec<u32>>>()
+                .map(Round::Number)
+                .map_or_else(invalid, Ok),
+        }
+    }
+}
+
+impl fmt::Display for Round {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Round::Unknown => f.write_str("?"),
+            Round::NotApplicable => f.write_str("-"),
+            Round::Number(ref parts) => {
+                for (i, part) in parts.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(".")?;
+                    }
+                    write!(f, "{}", part)?;
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
+impl FromStr for Round {
+    type Err = InvalidHeaderValue;
+
+    fn from_str(s: &str) -> Result<Round, InvalidHeaderValue> {
+        Round::from_bytes(s.as_bytes())
+    }
+}
+
+/// A period of the `TimeControl` header.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum TimePeriod {
+    /// `40/9000`: a number of moves in a number of seconds.
+    Moves { moves: u32, seconds: u32 },
+    /// `300`: the rest of the game in a number of seconds.
+    SuddenDeath { seconds: u32 },
+    /// `300+5`: the rest of the game in a number of seconds, with an
+    /// increment in seconds after each move.
+    Increment { seconds: u32, increment: u32 },
+    /// `*180`: a sandclock with a number of seconds.
+    Sandclock { seconds: u32 },
+}
+
+/// Parses a number of moves or seconds.
+fn number(s: &[u8]) -> Result<u32, InvalidHeaderValue> {
+    if !s.is_empty() && s.iter().all(|c| c.is_ascii_digit()) {
+        btou(s).or_else(|_| invalid())
+    } else {
+        invalid()
+    }
+}
+
+impl TimePeriod {
+    fn from_bytes(s: &[u8]) -> Result<TimePeriod, InvalidHeaderValue> {
+        if let Some(seconds) = s.strip_prefix(b"*") {
+            return Ok(TimePeriod::Sandclock { seconds: number(seconds)? });
+        }
+
+        if let Some(slash) = s.iter().position(|c| *c == b'/') {
+            Ok(TimePeriod::Moves { moves: number(&s[..slash])?, seconds: number(&s[slash + 1..])? })
+        } else if let Some(plus) = s.iter().position(|c| *c == b'+') {
+            Ok(TimePeriod::Increment { seconds: number(&s[..plus])?, increment: number(&s[plus + 1..])? })
+        } else {
+            Ok(TimePeriod::SuddenDeath { seconds: number(s)? })
+        }
+    }
+}
+
+impl fmt::Display for TimePeriod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TimePeriod::Moves { moves, seconds } => write!(f, "{}/{}", moves, seconds),
+            TimePeriod::SuddenDeath { seconds } => write!(f, "{}", seconds),
+            TimePeriod::Increment { seconds, increment } => write!(f, "{}+{}", seconds, increment),
+            TimePeriod::Sandclock { seconds } => write!(f, "*{}", seconds),
+        }
+    }
+}
+
+/// The value of the `TimeControl` header.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{TimeControl, TimePeriod};
+///
+/// assert_eq!(TimeControl::from_bytes(b"40/9000:300"), Ok(TimeControl::Periods(vec![
+///     TimePeriod::Moves { moves: 40, seconds: 9000 },
+///     TimePeriod::SuddenDeath { seconds: 300 },
+/// ])));
+/// assert_eq!(TimeControl::from_bytes(b"?"), Ok(TimeControl::Unknown));
+/// assert_eq!(TimeControl::from_bytes(b"-"), Ok(TimeControl::Unlimited));
+/// ```
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub enum TimeControl {
+    /// `?`
+    Unknown,
+    /// `-`, no time control.
+    Unlimited,
+    /// Periods separated by colons, like `40/9000:300`, played in order.
+    Periods(Vec<TimePeriod>),
+}
+
+impl TimeControl {
+    /// Parses a time control.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`InvalidHeaderValue`] if the input is neither `?`, `-` nor
+    /// periods like `40/9000`, `300`, `300+5` or `*180` separated by colons.
+    ///
+    /// [`InvalidHeaderValue`]: struct.InvalidHeaderValue.html
+    pub fn from_bytes(s: &[u8]) -> Result<TimeControl, InvalidHeaderValue> {
+        match s {
+            b"?" => Ok(TimeControl::Unknown),
+            b"-" => Ok(TimeControl::Unlimited),
+            _ => s.split(|c| *c == b':')
+                .map(TimePeriod::from_bytes)
+                .collect::<Result<Vec<_>, _>>()
+                .map(TimeControl::Periods),
+        }
+    }
+}
+
+impl fmt::Display for TimeControl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TimeControl::Unknown => f.write_str("?"),
+            TimeControl::Unlimited => f.write_str("-"),
+            TimeControl::Periods(ref periods) => {
+                for (i, period) in periods.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(":")?;
+                    }
+                    period.fmt(f)?;
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
+impl FromStr for TimeControl {
+    type Err = InvalidHeaderValue;
+
+    fn from_str(s: &str) -> Result<TimeControl, InvalidHeaderValue> {
+        TimeControl::from_bytes(s.as_bytes())
+    }
+}
+
+/// Lowercases ASCII letters and drops spaces, dashes and underscores, so
+/// that `Time forfeit` matches `timeforfeit`.
+fn normalize(s: &[u8]) -> Vec<u8> {
+    s.iter().filter(|c| !matches!(**c, b' ' | b'-' | b'_')).map(u8::to_ascii_lowercase).collect()
+}
+
+/// The value of the `Termination` header.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum Termination {
+    Abandoned,
+    Adjudication,
+    Death,
+    Emergency,
+    Normal,
+    RulesInfraction,
+    TimeForfeit,
+    Unterminated,
+}
+
+impl Termination {
+    /// Parses a termination, ignoring case.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`InvalidHeaderValue`] if the termination is not one of the
+    /// standard values, like `normal` or `time forfeit`.
+    ///
+    /// [`InvalidHeaderValue`]: struct.InvalidHeaderValue.html
+    pub fn from_bytes(s: &[u8]) -> Result<Termination, InvalidHeaderValue> {
+        match &normalize(s)[..] {
+            b"abandoned" => Ok(Termination::Abandoned),
+            b"adjudication" => Ok(Termination::Adjudication),
+            b"death" => Ok(Termination::Death),
+            b"emergency" => Ok(Termination::Emergency),
+            b"normal" => Ok(Termination::Normal),
+            b"rulesinfraction" => Ok(Termination::RulesInfraction),
+            b"timeforfeit" => Ok(Termination::TimeForfeit),
+            b"unterminated" => Ok(Termination::Unterminated),
+            _ => invalid(),
+        }
+    }
+}
+
+impl fmt::Display for Termination {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Termination::Abandoned => "abandoned",
+            Termination::Adjudication => "adjudication",
+            Termination::Death => "death",
+            Termination::Emergency => "emergency",
+            Termination::Normal => "normal",
+            Termination::RulesInfraction => "rules infraction",
+            Termination::TimeForfeit => "time forfeit",
+            Termination::Unterminated => "unterminated",
+        })
+    }
+}
+
+impl FromStr for Termination {
+    type Err = InvalidHeaderValue;
+
+    fn from_str(s: &str) -> Result<Termination, InvalidHeaderValue> {
+        Termination::from_bytes(s.as_bytes())
+    }
+}
+
+/// The value of the `Variant` header.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum Variant {
+    Standard,
+    /// Standard chess from a custom starting position (`From Position`).
+    FromPosition,
+    Chess960,
+    Crazyhouse,
+    Atomic,
+    Antichess,
+    KingOfTheHill,
+    ThreeCheck,
+    Horde,
+    RacingKings,
+}
+
+impl Variant {
+    /// Parses a variant, ignoring case and accepting common aliases like
+    /// `Fischerandom` or `Giveaway`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`InvalidHeaderValue`] if the variant is unknown.
+    ///
+    /// [`InvalidHeaderValue`]: struct.InvalidHeaderValue.html
+    pub fn from_bytes(s: &[u8]) -> Result<Variant, InvalidHeaderValue> {
+        match &normalize(s)[..] {
+            b"standard" | b"chess" | b"normal" => Ok(Variant::Standard),
+            b"fromposition" => Ok(Variant::FromPosition),
+            b"chess960" | b"fischerandom" | b"fischerrandom" => Ok(Variant::Chess960),
+            b"crazyhouse" => Ok(Variant::Crazyhouse),
+            b"atomic" => Ok(Variant::Atomic),
+            b"antichess" | b"giveaway" | b"suicide" => Ok(Variant::Antichess),
+            b"kingofthehill" | b"koth" => Ok(Variant::KingOfTheHill),
+            b"threecheck" | b"3check" => Ok(Variant::ThreeCheck),
+            b"horde" => Ok(Variant::Horde),
+            b"racingkings" => Ok(Variant::RacingKings),
+            _ => invalid(),
+        }
+    }
+}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Variant::Standard => "Standard",
+            Variant::FromPosition => "From Position",
+            Variant::Chess960 => "Chess960",
+            Variant::Crazyhouse => "Crazyhouse",
+            Variant::Atomic => "Atomic",
+            Variant::Antichess => "Antichess",
+            Variant::KingOfTheHill => "King of the Hill",
+            Variant::ThreeCheck => "Three-check",
+            Variant::Horde => "Horde",
+            Variant::RacingKings => "Racing Kings",
+        })
+    }
+}
+
+impl FromStr for Variant {
+    type Err = InvalidHeaderValue;
+
+    fn from_str(s: &str) -> Result<Variant, InvalidHeaderValue> {
+        Variant::from_bytes(s.as_bytes())
+    }
+}
+
+/// Game headers with typed accessors. Also a visitor that collects the
+/// headers of each game, skipping the movetext.
+///
+/// The typed accessors return `None` if the header is missing or its value
+/// is invalid. Use [`get()`](#method.get) and the parsers like
+/// [`Date::from_bytes()`] to tell the difference.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{Reader, Headers, Date, Round};
+///
+/// let pgn = b"[Event \"Casual\"]\n[Date \"2017.??.??\"]\n[Round \"3.1\"]\n\
+///             [WhiteElo \"2850\"]\n[BlackElo \"?\"]\n\n1. e4 *";
+///
+/// let mut headers = Headers::new();
+/// let headers = Reader::new(&mut headers, pgn).read_game().unwrap();
+///
+/// assert_eq!(headers.event(), Some(&b"Casual"[..]));
+/// assert_eq!(headers.date(), Some(Date { year: Some(2017), month: None, day: None }));
+/// assert_eq!(headers.round(), Some(Round::Number(vec![3, 1])));
+/// assert_eq!(headers.white_elo(), Some(2850));
+/// assert_eq!(headers.black_elo(), None);
+/// ```
+///
+/// [`Date::from_bytes()`]: struct.Date.html#method.from_bytes
+#[derive(Clone, Default, Eq, PartialEq, Debug)]
+pub struct Headers {
+    headers: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+impl Headers {
+    /// Creates an empty header collection.
+    pub fn new() -> Headers {
+        Headers::default()
+    }
+
+    /// Gets the unescaped value of the first header with the given key.
+    pub fn get(&self, key: &[u8]) -> Option<&[u8]> {
+        self.headers.iter().find(|(k, _)| k == key).map(|(_, v)| &v[..])
+    }
+
+    /// Iterates over all headers in the order they appear.
+    pub fn iter(&self) -> impl Iterator<Item=(&[u8], &[u8])> {
+        self.headers.iter().map(|(k, v)| (&k[..], &v[..]))
+    }
+
+    /// Gets the number of headers.
+    pub fn len(&self) -> usize {
+        self.headers.len()
+    }
+
+    /// Tests if there are no headers.
+    pub fn is_empty(&self) -> bool {
+        self.headers.is_empty()
+    }
+
+    fn parse<T, F>(&self, key: &[u8], parser: F) -> Option<T>
+        where F: FnOnce(&[u8]) -> Result<T, InvalidHeaderValue>
+    {
+        self.get(key).and_then(|value| parser(value).ok())
+    }
+
+    /// The `Event` header.
+    pub fn event(&self) -> Option<&[u8]> {
+        self.get(b"Event")
+    }
+
+    /// The `Site` header.
+    pub fn site(&self) -> Option<&[u8]> {
+        self.get(b"Site")
+    }
+
+    /// The `Date` header.
+    pub fn date(&self) -> Option<Date> {
+        self.parse(b"Date", Date::from_bytes)
+    }
+
+    /// The `Round` header.
+    pub fn round(&self) -> Option<Round> {
+        self.parse(b"Round", Round::from_bytes)
+    }
+
+    /// The `White` header.
+    pub fn white(&self) -> Option<&[u8]> {
+        self.get(b"White")
+    }
+
+    /// The `Black` header.
+    pub fn black(&self) -> Option<&[u8]> {
+        self.get(b"Black")
+    }
+
+    /// The `Result` header, with `Some(None)` for `*`.
+    pub fn result(&self) -> Option<Option<Outcome>> {
+        self.get(b"Result").and_then(|value| RawHeader(value).outcome().ok())
+    }
+
+    /// The `WhiteElo` header.
+    pub fn white_elo(&self) -> Option<u16> {
+        self.parse(b"WhiteElo", |v| partial(v, 4).and_then(|elo| elo.map_or_else(invalid, Ok)))
+    }
+
+    /// The `BlackElo` header.
+    pub fn black_elo(&self) -> Option<u16> {
+        self.parse(b"BlackElo", |v| partial(v, 4).and_then(|elo| elo.map_or_else(invalid, Ok)))
+    }
+
+    /// The `EventDate` header.
+    pub fn event_date(&self) -> Option<Date> {
+        self.parse(b"EventDate", Date::from_bytes)
+    }
+
+    /// The `UTCDate` header.
+    pub fn utc_date(&self) -> Option<Date> {
+        self.parse(b"UTCDate", Date::from_bytes)
+    }
+
+    /// The `UTCTime` header.
+    pub fn utc_time(&self) -> Option<Time> {
+        self.parse(b"UTCTime", Time::from_bytes)
+    }
+
+    /// The `ECO` header, like `B90`.
+    pub fn eco(&self) -> Option<&[u8]> {
+        self.get(b"ECO").filter(|v| matches!(v, [b'A'..=b'E', b'0'..=b'9', b'0'..=b'9']))
+    }
+
+    /// The `TimeControl` header.
+    pub fn time_control(&self) -> Option<TimeControl> {
+        self.parse(b"TimeControl", TimeControl::from_bytes)
+    }
+
+    /// The `Termination` header.
+    pub fn termination(&self) -> Option<Termination> {
+        self.parse(b"Termination", Termination::from_bytes)
+    }
+
+    /// The `SetUp` header: `0` or `1`.
+    pub fn setup(&self) -> Option<bool> {
+        match self.get(b"SetUp") {
+            Some(b"0") => Some(false),
+            Some(b"1") => Some(true),
+            _ => None,
+        }
+    }
+
+    /// The `FEN` header.
+    pub fn fen(&self) -> Option<Fen> {
+        self.get(b"FEN").and_then(|value| Fen::from_bytes(value).ok())
+    }
+
+    /// The `PlyCount` header.
+    pub fn ply_count(&self) -> Option<u32> {
+        self.parse(b"PlyCount", |v| if v.iter().all(|c| c.is_ascii_digit()) { btou(v).or_else(|_| invalid()) } else { invalid() })
+    }
+
+    /// The `Variant` header.
+    pub fn variant(&self) -> Option<Variant> {
+        self.parse(b"Variant", Variant::from_bytes)
+    }
+}
+
+impl From<Vec<(Vec<u8>, Vec<u8>)>> for Headers {
+    fn from(headers: Vec<(Vec<u8>, Vec<u8>)>) -> Headers {
+        Headers { headers }
+    }
+}
+
+impl<'pgn> Visitor<'pgn> for Headers {
+    type Result = Headers;
+
+    fn begin_game(&mut self) {
+        self.headers.clear();
+    }
+
+    fn header(&mut self, key: &'pgn [u8], value: RawHeader<'pgn>) {
+        self.headers.push((key.to_vec(), value.decode().into_owned()));
+    }
+
+    fn end_headers(&mut self) -> Skip {
+        Skip(true)
+    }
+
+    fn end_game(&mut self, _game: &'pgn [u8]) -> Headers {
+        self.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {Reader, Color};
+
+    #[test]
+    fn test_parsers() {
+        assert_eq!(Date::from_bytes(b"????.??.??"), Ok(Date::default()));
+        assert_eq!(Date::from_bytes(b"1992.11.04").map(|d| d.to_string()), Ok("1992.11.04".to_owned()));
+        assert_eq!("2017.??.05".parse(), Ok(Date { year: Some(2017), month: None, day: Some(5) }));
+        assert!(Date::from_bytes(b"2017.13.??").is_err());
+        assert!(Date::from_bytes(b"2017.1?.??").is_err());
+        assert!(Date::from_bytes(b"2017.01").is_err());
+        assert!(Date::from_bytes(b"2017.02.31").is_err());
+        assert!(Date::from_bytes(b"2017.02.29").is_err());
+        assert!(Date::from_bytes(b"2000.02.29").is_ok());
+        assert!(Date::from_bytes(b"1900.02.29").is_err());
+        assert!(Date::from_bytes(b"????.02.29").is_ok());
+        assert!(Date::from_bytes(b"2017.04.31").is_err());
+        assert!(Date::from_bytes(b"2017.??.31").is_ok());
+
+        assert_eq!(Time::from_bytes(b"23:59:07"), Ok(Time { hour: 23, minute: 59, second: 7 }));
+        assert!(Time::from_bytes(b"24:00:00").is_err());
+        assert!(Time::from_bytes(b"??:00:00").is_err());
+
+        assert_eq!(Round::from_bytes(b"12"), Ok(Round::Number(vec![12])));
+        assert!(Round::from_bytes(b"3.").is_err());
+        assert!(Round::from_bytes(b"").is_err());
+        assert_eq!(Round::Number(vec![3, 1]).to_string(), "3.1");
+
+        assert_eq!(TimeControl::from_bytes(b"300+5"), Ok(TimeControl::Periods(vec![TimePeriod::Increment { seconds: 300, increment: 5 }])));
+        assert_eq!(TimeControl::from_bytes(b"*180"), Ok(TimeControl::Periods(vec![TimePeriod::Sandclock { seconds: 180 }])));
+        assert_eq!(TimeControl::from_bytes(b"40/7200:20/3600:900+30").map(|tc| tc.to_string()), Ok("40/7200:20/3600:900+30".to_owned()));
+        assert!(TimeControl::from_bytes(b"").is_err());
+        assert!(TimeControl::from_bytes(b"40/").is_err());
+        assert!(TimeControl::from_bytes(b"300:").is_err());
+        assert!(TimeControl::from_bytes(b"5+").is_err());
+        assert!(TimeControl::from_bytes(b"*").is_err());
+        assert!(TimeControl::from_bytes(b"40/9000+5/").is_err());
+
+        assert_eq!(Termination::from_bytes(b"Time forfeit"), Ok(Termination::TimeForfeit));
+        assert_eq!(Termination::from_bytes(b"rules infraction"), Ok(Termination::RulesInfraction));
+        assert_eq!(Variant::from_bytes(b"Fischerandom"), Ok(Variant::Chess960));
+        assert_eq!(Variant::from_bytes(b"King of the Hill"), Ok(Variant::KingOfTheHill));
+        assert!(Variant::from_bytes(b"Shogi").is_err());
+    }
+
+    #[test]
+    fn test_collect() {
+        let pgn = b"[Event \"A \\\"B\\\"\"]\n[Result \"0-1\"]\n[UTCDate \"2018.01.02\"]\n[UTCTime \"10:00:00\"]\n\
+                    [SetUp \"1\"]\n[FEN \"8/8/8/8/8/8/8/K6k w - - 0 1\"]\n[PlyCount \"?\"]\n\
+                    [Termination \"normal\"]\n[Variant \"From Position\"]\n[ECO \"A00\"]\n\
+                    [TimeControl \"300+5\"]\n\n1. Kb1 0-1\n\n\
+                    [Result \"*\"]\n\n*";
+
+        let mut headers = Headers::new();
+        let games: Vec<_> = Reader::new(&mut headers, pgn).into_iter().collect();
+        assert_eq!(games.len(), 2);
+
+        let first = &games[0];
+        assert_eq!(first.event(), Some(&b"A \"B\""[..]));
+        assert_eq!(first.result(), Some(Some(Outcome::Decisive { winner: Color::Black })));
+        assert_eq!(first.utc_date(), Some(Date { year: Some(2018), month: Some(1), day: Some(2) }));
+        assert_eq!(first.utc_time().map(|t| t.to_string()), Some("10:00:00".to_owned()));
+        assert_eq!(first.setup(), Some(true));
+        assert!(first.fen().is_some());
+        assert_eq!(first.ply_count(), None);
+        assert_eq!(first.termination(), Some(Termination::Normal));
+        assert_eq!(first.variant(), Some(Variant::FromPosition));
+        assert_eq!(first.eco(), Some(&b"A00"[..]));
+        assert_eq!(first.time_control(), Some(TimeControl::Periods(vec![TimePeriod::Increment { seconds: 300, increment: 5 }])));
+        assert_eq!(first.date(), None);
+
+        assert_eq!(games[1].len(), 1);
+        assert_eq!(games[1].result(), Some(None));
+    }
+}
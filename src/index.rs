@@ -0,0 +1,610 @@
+// This file is part of the pgn-reader library.
+// Copyright (C) 2017 Niklas Fiekas <niklas.fiekas@backscattering.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use {Reader, Visitor, RawHeader, Span, Location, LineCounter, is_space};
+
+/// Magic number and format version at the start of an index file.
+const MAGIC: &[u8] = b"PGNIDX\x01";
+
+/// A visitor that builds an [`Index`] of the games it visits.
+///
+/// Records the byte offset and location of each game and the values of the selected
+/// headers. Works with [`Reader`] and [`BufferedReader`].
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{Reader, IndexBuilder};
+///
+/// let pgn = b"[White \"Carlsen\"]\n\n1. e4 *\n\n[White \"Caruana\"]\n\n1. d4 *";
+///
+/// let mut builder = IndexBuilder::new(&["White"]);
+/// Reader::new(&mut builder, pgn).read_all();
+/// let index = builder.into_index();
+///
+/// assert_eq!(index.len(), 2);
+/// assert_eq!(index.offset(1), Some(28));
+/// assert_eq!(index.get(1, b"White"), Some(&b"Caruana"[..]));
+/// ```
+///
+/// [`Index`]: struct.Index.html
+/// [`Reader`]: struct.Reader.html
+/// [`BufferedReader`]: struct.BufferedReader.html
+#[derive(Debug)]
+pub struct IndexBuilder {
+    index: Index,
+    values: Vec<Option<Vec<u8>>>,
+    span: Span,
+}
+
+impl IndexBuilder {
+    /// Creates a builder that records the given header fields.
+    pub fn new<K: AsRef<[u8]>>(keys: &[K]) -> IndexBuilder {
+        IndexBuilder {
+            index: Index {
+                keys: keys.iter().map(|k| k.as_ref().to_vec()).collect(),
+                offsets: Vec::new(),
+                locations: Vec::new(),
+                values: Vec::new(),
+            },
+            values: vec![None; keys.len()],
+            span: Span::default(),
+        }
+    }
+
+    /// Gets the index of all games visited so far.
+    pub fn into_index(self) -> Index {
+        self.index
+    }
+}
+
+impl<'pgn> Visitor<'pgn> for IndexBuilder {
+    type Result = ();
+
+    fn begin_game(&mut self) {
+        for value in &mut self.values {
+            *value = None;
+        }
+    }
+
+    fn span(&mut self, span: Span) {
+        self.span = span;
+    }
+
+    fn header(&mut self, key: &'pgn [u8], value: RawHeader<'pgn>) {
+        if let Some(pos) = self.index.keys.iter().position(|k| k == key) {
+            self.values[pos] = Some(value.decode().into_owned());
+        }
+    }
+
+    fn end_headers(&mut self) -> ::Skip {
+        ::Skip(true)
+    }
+
+    fn end_game(&mut self, game: &'pgn [u8]) {
+        if game.iter().all(|c| is_space(*c)) {
+            return;
+        }
+
+        self.index.offsets.push(self.span.start);
+        self.index.locations.push(self.span.location);
+        for value in &mut self.values {
+            self.index.values.push(value.take().map(Vec::into_boxed_slice));
+        }
+    }
+}
+
+/// Byte offsets, locations and selected header values of the games in a
+/// PGN.
+///
+/// Built using an [`IndexBuilder`] and stored in a compact sidecar file
+/// with [`write()`](#method.write) and [`read()`](#method.read). Use
+/// [`Reader::at_game()`] and [`Reader::filter()`] to jump directly to
+/// indexed games.
+///
+/// [`IndexBuilder`]: struct.IndexBuilder.html
+/// [`Reader::at_game()`]: struct.Reader.html#method.at_game
+/// [`Reader::filter()`]: struct.Reader.html#method.filter
+#[derive(Clone, Eq, PartialEq)]
+pub struct Index {
+    keys: Vec<Vec<u8>>,
+    offsets: Vec<usize>,
+    locations: Vec<Location>,
+    values: Vec<Option<Box<[u8]>>>,
+}
+
+impl fmt::Debug for Index {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Index")
+            .field("keys", &self.keys.len())
+            .field("games", &self.offsets.len())
+            .finish()
+    }
+}
+
+impl Index {
+    /// Number of indexed games.
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Tests if there are no indexed games.
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// Gets the header fields that are recorded for each game.
+    pub fn keys(&self) -> &[Vec<u8>] {
+        &self.keys
+    }
+
+    /// Gets the byte offset of game `n` (counting from 0).
+    pub fn offset(&self, n: usize) -> Option<usize> {
+        self.offsets.get(n).cloned()
+    }
+
+    /// Gets the line and column of game `n` (counting from 0).
+    pub fn location(&self, n: usize) -> Option<Location> {
+        self.locations.get(n).cloned()
+    }
+
+    /// Gets the unescaped value of an indexed header of game `n`, or `None` if
+    /// the game does not have this header or it was not indexed.
+    pub fn get(&self, n: usize, key: &[u8]) -> Option<&[u8]> {
+        let pos = self.keys.iter().position(|k| k.as_slice() == key)?;
+        self.entry(n)?.value(pos)
+    }
+
+    /// Gets the indexed data of game `n`.
+    pub fn entry(&self, n: usize) -> Option<Entry<'_>> {
+        if n < self.len() {
+            Some(Entry { index: self, n })
+        } else {
+            None
+        }
+    }
+
+    /// Writes the index.
+    ///
+    /// # Errors
+    ///
+    /// Returns any I/O error of the writer. Returns an error of kind
+    /// `InvalidData` if the offsets are not in increasing order, like when
+    /// the same input was visited twice.
+    pub fn write<W: Write>(&self, mut w: W) -> io::Result<()> {
+        w.write_all(MAGIC)?;
+
+        write_varint(&mut w, self.keys.len() as u64)?;
+        for key in &self.keys {
+            write_bytes(&mut w, key)?;
+        }
+
+        write_varint(&mut w, self.offsets.len() as u64)?;
+        let mut prev = 0;
+        let mut prev_line = 1;
+        for (n, (&offset, location)) in self.offsets.iter().zip(&self.locations).enumerate() {
+            let delta = offset.checked_sub(prev).ok_or_else(|| invalid_data("offsets not in order"))?;
+            write_varint(&mut w, delta as u64)?;
+            prev = offset;
+
+            let lines = location.line.checked_sub(prev_line).ok_or_else(|| invalid_data("offsets not in order"))?;
+            write_varint(&mut w, lines as u64)?;
+            write_varint(&mut w, location.column as u64)?;
+            prev_line = location.line;
+
+            for value in &self.values[n * self.keys.len()..(n + 1) * self.keys.len()] {
+                match *value {
+                    // Store length + 1, so that 0 can mark missing values.
+                    Some(ref value) => {
+                        write_varint(&mut w, value.len() as u64 + 1)?;
+                        w.write_all(value)?;
+                    },
+                    None => write_varint(&mut w, 0)?,
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads an index that was previously written using
+    /// [`write()`](#method.write).
+    ///
+    /// # Errors
+    ///
+    /// Returns any I/O error of the reader. Returns an error of kind
+    /// `InvalidData` if the input is not a valid index.
+    pub fn read<R: Read>(mut r: R) -> io::Result<Index> {
+        let mut magic = [0; 7];
+        r.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(invalid_data("not a pgn index"));
+        }
+
+        let num_keys = read_len(&mut r)?;
+        let keys = (0..num_keys).map(|_| {
+            let len = read_len(&mut r)?;
+            read_vec(&mut r, len)
+        }).collect::<io::Result<Vec<_>>>()?;
+
+        let num_games = read_len(&mut r)?;
+        let mut offsets = Vec::new();
+        let mut locations = Vec::new();
+        let mut values = Vec::new();
+        let mut offset = 0usize;
+        let mut line = 1usize;
+        for _ in 0..num_games {
+            offset = offset.checked_add(read_len(&mut r)?).ok_or_else(|| invalid_data("offset too large"))?;
+            offsets.push(offset);
+
+            line = line.checked_add(read_len(&mut r)?).ok_or_else(|| invalid_data("line too large"))?;
+            let column = read_len(&mut r)?;
+            locations.push(Location { line, column });
+
+            for _ in 0..num_keys {
+                values.push(match read_len(&mut r)? {
+                    0 => None,
+                    len => Some(read_vec(&mut r, len - 1)?.into_boxed_slice()),
+                });
+            }
+        }
+
+        Ok(Index { keys, offsets, locations, values })
+    }
+}
+
+/// The indexed data of a single game.
+#[derive(Copy, Clone)]
+pub struct Entry<'i> {
+    index: &'i Index,
+    n: usize,
+}
+
+impl<'i> fmt::Debug for Entry<'i> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Entry")
+            .field("game", &self.n)
+            .field("offset", &self.offset())
+            .finish()
+    }
+}
+
+impl<'i> Entry<'i> {
+    /// Gets the number of the game (counting from 0).
+    pub fn game(&self) -> usize {
+        self.n
+    }
+
+    /// Gets the byte offset of the game.
+    pub fn offset(&self) -> usize {
+        self.index.offsets[self.n]
+    }
+
+    /// Gets the line and column of the game.
+    pub fn location(&self) -> Location {
+        self.index.locations[self.n]
+    }
+
+    /// Gets the unescaped value of an indexed header.
+    pub fn get(&self, key: &[u8]) -> Option<&'i [u8]> {
+        let pos = self.index.keys.iter().position(|k| k.as_slice() == key)?;
+        self.value(pos)
+    }
+
+    fn value(&self, pos: usize) -> Option<&'i [u8]> {
+        self.index.values[self.n * self.index.keys.len() + pos].as_ref().map(|v| &v[..])
+    }
+}
+
+impl<'a, 'pgn, V: Visitor<'pgn>> Reader<'a, 'pgn, V> {
+    /// Creates a reader that starts at game `n` of an [`Index`] built from
+    /// `pgn`. Returns `None` if there is no such game, or if its offset is not
+    /// at the start of a game in `pgn`, i.e. at a `[` that begins a line, or
+    /// after a blank line.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pgn_reader::{Reader, IndexBuilder, Visitor};
+    ///
+    /// struct Game;
+    ///
+    /// impl<'pgn> Visitor<'pgn> for Game {
+    ///     type Result = &'pgn [u8];
+    ///
+    ///     fn end_game(&mut self, game: &'pgn [u8]) -> &'pgn [u8] {
+    ///         game
+    ///     }
+    /// }
+    ///
+    /// let pgn = b"1. e4 *\n\n1. d4 *\n\n1. c4 *\n";
+    ///
+    /// let mut builder = IndexBuilder::new::<&str>(&[]);
+    /// Reader::new(&mut builder, pgn).read_all();
+    /// let index = builder.into_index();
+    ///
+    /// let mut visitor = Game;
+    /// let mut reader = Reader::at_game(&mut visitor, pgn, &index, 1).unwrap();
+    /// assert_eq!(reader.read_game(), Some(&b"1. d4 *\n\n"[..]));
+    /// ```
+    ///
+    /// [`Index`]: struct.Index.html
+    pub fn at_game(visitor: &'a mut V, pgn: &'pgn [u8], index: &Index, n: usize) -> Option<Reader<'a, 'pgn, V>> {
+        let offset = index.offset(n)?;
+        if is_game_start(pgn, offset) {
+            let lines = LineCounter::new(offset, index.location(n)?);
+            Some(Reader::with_offset(visitor, &pgn[offset..], lines))
+        } else {
+            None
+        }
+    }
+
+    /// Reads only the games of an [`Index`] built from `pgn` whose
+    /// indexed headers match `predicate`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pgn_reader::{Reader, IndexBuilder, Visitor, SanPlus};
+    ///
+    /// struct FirstMove(Option<SanPlus>);
+    ///
+    /// impl<'pgn> Visitor<'pgn> for FirstMove {
+    ///     type Result = Option<SanPlus>;
+    ///
+    ///     fn san(&mut self, san_plus: SanPlus) {
+    ///         if self.0.is_none() {
+    ///             self.0 = Some(san_plus);
+    ///         }
+    ///     }
+    ///
+    ///     fn end_game(&mut self, _game: &'pgn [u8]) -> Option<SanPlus> {
+    ///         self.0.take()
+    ///     }
+    /// }
+    ///
+    /// let pgn = b"[White \"A\"]\n1. e4 *\n\n[White \"B\"]\n1. d4 *\n\n[White \"A\"]\n1. c4 *";
+    ///
+    /// let mut builder = IndexBuilder::new(&["White"]);
+    /// Reader::new(&mut builder, pgn).read_all();
+    /// let index = builder.into_index();
+    ///
+    /// let mut visitor = FirstMove(None);
+    /// let moves: Vec<_> = Reader::filter(&mut visitor, pgn, &index, |e| e.get(b"White") == Some(b"A"))
+    ///     .map(|san| san.unwrap().to_string())
+    ///     .collect();
+    ///
+    /// assert_eq!(moves, vec!["e4", "c4"]);
+    /// ```
+    ///
+    /// [`Index`]: struct.Index.html
+    pub fn filter<'i, F>(visitor: &'a mut V, pgn: &'pgn [u8], index: &'i Index, predicate: F) -> Filter<'a, 'pgn, 'i, V, F>
+        where F: FnMut(Entry<'i>) -> bool
+    {
+        Filter { visitor, pgn, index, predicate, next: 0 }
+    }
+}
+
+/// Iterator over the games of an [`Index`] that match a predicate, created
+/// by [`Reader::filter()`]. Entries that [`Reader::at_game()`] rejects, and
+/// entries with only whitespace at their offset, are skipped.
+///
+/// [`Index`]: struct.Index.html
+/// [`Reader::filter()`]: struct.Reader.html#method.filter
+/// [`Reader::at_game()`]: struct.Reader.html#method.at_game
+pub struct Filter<'a, 'pgn, 'i, V, F> where V: 'a {
+    visitor: &'a mut V,
+    pgn: &'pgn [u8],
+    index: &'i Index,
+    predicate: F,
+    next: usize,
+}
+
+impl<'a, 'pgn, 'i, V, F> fmt::Debug for Filter<'a, 'pgn, 'i, V, F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Filter").finish()
+    }
+}
+
+impl<'a, 'pgn, 'i, V, F> Iterator for Filter<'a, 'pgn, 'i, V, F>
+    where V: Visitor<'pgn>,
+          F: FnMut(Entry<'i>) -> bool
+{
+    type Item = V::Result;
+
+    fn next(&mut self) -> Option<V::Result> {
+        while let Some(entry) = self.index.entry(self.next) {
+            self.next += 1;
+            if (self.predicate)(entry) {
+                // Skip entries that do not point to a game, like those of a
+                // stale index.
+                let result = Reader::at_game(self.visitor, self.pgn, self.index, entry.game())
+                    .and_then(|mut reader| reader.read_game());
+                if result.is_some() {
+                    return result;
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Tests if `offset` can be the start of a game: at the start of the input,
+/// or after a line break (and indentation) and either at a tag pair or
+/// after a blank line.
+fn is_game_start(pgn: &[u8], offset: usize) -> bool {
+    if offset > pgn.len() {
+        return false;
+    }
+
+    let head = &pgn[..offset];
+    let head = &head[..head.iter().rposition(|c| !matches!(*c, b' ' | b'\t')).map_or(0, |p| p + 1)];
+    if head.is_empty() || head == b"\xef\xbb\xbf" {
+        true
+    } else if pgn[offset..].starts_with(b"[") {
+        head.ends_with(b"\n")
+    } else {
+        head.ends_with(b"\n\n") || head.ends_with(b"\n\r\n")
+    }
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn write_varint<W: Write>(w: &mut W, mut n: u64) -> io::Result<()> {
+    while n >= 0x80 {
+        w.write_all(&[(n as u8) | 0x80])?;
+        n >>= 7;
+    }
+    w.write_all(&[n as u8])
+}
+
+fn write_bytes<W: Write>(w: &mut W, bytes: &[u8]) -> io::Result<()> {
+    write_varint(w, bytes.len() as u64)?;
+    w.write_all(bytes)
+}
+
+fn read_varint<R: Read>(r: &mut R) -> io::Result<u64> {
+    let mut n = 0;
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0];
+        r.read_exact(&mut byte)?;
+        n |= u64::from(byte[0] & 0x7f) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(n);
+        }
+    }
+    Err(invalid_data("varint too long"))
+}
+
+fn read_len<R: Read>(r: &mut R) -> io::Result<usize> {
+    let n = read_varint(r)?;
+    if n > usize::MAX as u64 {
+        Err(invalid_data("length too large"))
+    } else {
+        Ok(n as usize)
+    }
+}
+
+fn read_vec<R: Read>(r: &mut R, len: usize) -> io::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    r.take(len as u64).read_to_end(&mut buf)?;
+    if buf.len() == len {
+        Ok(buf)
+    } else {
+        Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated pgn index"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::BufReader;
+    use BufferedReader;
+
+    #[test]
+    fn test_roundtrip() {
+        let pgn = b"\xef\xbb\xbf[Event \"A\"]\n[White \"W\"]\n\n1. e4 *\n\n\
+                    [Event \"B\"]\n\n1. d4 { long\n\ncomment } *\n\n\
+                    1. c4 *\n";
+
+        let mut builder = IndexBuilder::new(&["Event", "White"]);
+        Reader::new(&mut builder, &pgn[..]).read_all();
+        let index = builder.into_index();
+
+        assert_eq!(index.len(), 3);
+        assert_eq!(index.offset(0), Some(3));
+        assert_eq!(index.get(0, b"Event"), Some(&b"A"[..]));
+        assert_eq!(index.get(0, b"White"), Some(&b"W"[..]));
+        assert_eq!(index.get(1, b"White"), None);
+        assert_eq!(index.get(2, b"Event"), None);
+        assert!(pgn[index.offset(2).unwrap()..].starts_with(b"1. c4"));
+        assert_eq!(index.location(2), Some(Location { line: 12, column: 1 }));
+        assert_eq!(index.entry(0).map(|e| e.location()), Some(Location { line: 1, column: 4 }));
+
+        let mut visitor = GameLocation(Location::default());
+        let mut reader = Reader::at_game(&mut visitor, &pgn[..], &index, 2).expect("game 2");
+        assert_eq!(reader.read_game(), Some(Location { line: 12, column: 1 }));
+
+        let mut builder = IndexBuilder::new(&["Event", "White"]);
+        BufferedReader::new(&mut builder, BufReader::with_capacity(4, &pgn[..])).read_all().expect("read");
+        assert_eq!(builder.into_index(), index);
+
+        let mut file = Vec::new();
+        index.write(&mut file).expect("write");
+        assert_eq!(Index::read(&file[..]).expect("read"), index);
+        assert!(Index::read(&file[..file.len() - 1]).is_err());
+        assert!(Index::read(&b"not an index"[..]).is_err());
+    }
+
+    struct GameLocation(Location);
+
+    impl<'pgn> Visitor<'pgn> for GameLocation {
+        type Result = Location;
+
+        fn span(&mut self, span: Span) {
+            self.0 = span.location;
+        }
+
+        fn end_game(&mut self, _game: &'pgn [u8]) -> Location {
+            self.0
+        }
+    }
+
+    struct Game;
+
+    impl<'pgn> Visitor<'pgn> for Game {
+        type Result = &'pgn [u8];
+
+        fn end_game(&mut self, game: &'pgn [u8]) -> &'pgn [u8] {
+            game
+        }
+    }
+
+    #[test]
+    fn test_stale() {
+        let pgn = b"1. e4 *\n\n1. d4 *\n\n";
+
+        let index = Index {
+            keys: Vec::new(),
+            offsets: vec![0, 1000, 3, 18, 9],
+            locations: vec![
+                Location { line: 1, column: 1 },
+                Location { line: 3, column: 1 },
+                Location { line: 1, column: 4 },
+                Location { line: 5, column: 1 },
+                Location { line: 3, column: 1 },
+            ],
+            values: Vec::new(),
+        };
+
+        let games: Vec<_> = Reader::filter(&mut Game, &pgn[..], &index, |_| true).collect();
+        assert_eq!(games, vec![&b"1. e4 *\n\n"[..], &b"1. d4 *\n\n"[..]]);
+
+        let err = index.write(Vec::new()).expect_err("offsets not in order");
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        assert!(is_game_start(b"\xef\xbb\xbf[Event \"?\"]", 3));
+        assert!(is_game_start(b"*\n  [Event \"?\"]", 4));
+        assert!(is_game_start(b"*\r\n\r\n1. e4 *", 5));
+        assert!(!is_game_start(b"1. e4 { [%clk 0:01:00] } *", 8));
+        assert!(!is_game_start(b"1. e4\n2. d4 *", 6));
+    }
+}
@@ -0,0 +1,433 @@
+// This file is part of the pgn-reader library.
+// Copyright (C) 2017 Niklas Fiekas <niklas.fiekas@backscattering.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt;
+use std::mem;
+use std::error::Error;
+
+use shakmaty::{Chess, Move, Position};
+use shakmaty::fen::Fen;
+use shakmaty::san::SanError;
+
+use {Visitor, Skip, Stop, RawHeader, SanPlus, San, Uci, Nag, Color, Outcome,
+     SyntaxError, Span};
+
+/// A move or starting position that could not be played.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum LegalError {
+    /// A `FEN` header that can not be parsed.
+    InvalidFen,
+    /// A `FEN` header with an illegal position, like one without kings.
+    IllegalPosition,
+    /// A move that is not legal in the current position.
+    IllegalMove,
+    /// A move in SAN that matches more than one legal move.
+    AmbiguousMove,
+    /// A null move in a position where it is not allowed, like when in
+    /// check.
+    IllegalNullMove,
+}
+
+impl LegalError {
+    fn desc(&self) -> &str {
+        match *self {
+            LegalError::InvalidFen => "invalid fen",
+            LegalError::IllegalPosition => "illegal position",
+            LegalError::IllegalMove => "illegal move",
+            LegalError::AmbiguousMove => "ambiguous move",
+            LegalError::IllegalNullMove => "illegal null move",
+        }
+    }
+}
+
+impl fmt::Display for LegalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.desc().fmt(f)
+    }
+}
+
+impl Error for LegalError {
+    fn description(&self) -> &str {
+        self.desc()
+    }
+}
+
+/// A [`Visitor`] that also receives the moves of the game resolved against
+/// the current position. Used with [`Legal`].
+///
+/// [`Visitor`]: trait.Visitor.html
+/// [`Legal`]: struct.Legal.html
+pub trait LegalVisitor<'pgn>: Visitor<'pgn> {
+    /// Called after `san` or `uci` with the legal move and the positions
+    /// before and after it.
+    fn play(&mut self, _before: &Chess, _m: &Move, _after: &Chess) { }
+    /// Called after `san` for a null move, with the positions before and
+    /// after passing the turn.
+    fn null_move(&mut self, _before: &Chess, _after: &Chess) { }
+    /// Called for the first move of a line that can not be played, or after
+    /// `header` for a bad `FEN` header, with the current position. The rest
+    /// of the line is not resolved, but variations of the failed move and of
+    /// earlier moves and the continuation of enclosing lines still are. After
+    /// a bad `FEN` header no moves of the game are resolved.
+    fn illegal_move(&mut self, _error: LegalError, _pos: &Chess) { }
+}
+
+/// The position at the end of a line.
+#[derive(Clone, Debug, Default)]
+struct Frame {
+    pos: Chess,
+    /// The position before the last move, where a variation would start.
+    before: Option<Chess>,
+    failed: bool,
+    /// Whether the last move of the line is the one that could not be played.
+    failed_last: bool,
+}
+
+/// Adapts a [`LegalVisitor`], keeping track of the current position,
+/// including in variations.
+///
+/// All [`Visitor`] methods are forwarded to the inner visitor. Each `san` and
+/// `uci` is then followed by a call to [`play`], [`null_move`] or
+/// [`illegal_move`]. Games start from the standard starting position, or the
+/// position in the `FEN` header.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate pgn_reader;
+/// # extern crate shakmaty;
+/// use pgn_reader::{Reader, Visitor, Legal, LegalVisitor, LegalError};
+/// use shakmaty::{Chess, Move, Position};
+///
+/// #[derive(Default)]
+/// struct Checkmates {
+///     mates: usize,
+///     errors: usize,
+/// }
+///
+/// impl<'pgn> Visitor<'pgn> for Checkmates {
+///     type Result = (usize, usize);
+///
+///     fn end_game(&mut self, _game: &'pgn [u8]) -> Self::Result {
+///         (self.mates, self.errors)
+///     }
+/// }
+///
+/// impl<'pgn> LegalVisitor<'pgn> for Checkmates {
+///     fn play(&mut self, _before: &Chess, _m: &Move, after: &Chess) {
+///         if after.is_checkmate() {
+///             self.mates += 1;
+///         }
+///     }
+///
+///     fn illegal_move(&mut self, _error: LegalError, _pos: &Chess) {
+///         self.errors += 1;
+///     }
+/// }
+///
+/// # fn main() {
+/// let pgn = b"1. f3 e5 2. g4 Qh4 (2... Qh4# 3. Ke2) 3. Nf9 *";
+///
+/// let mut legal = Legal::new(Checkmates::default());
+/// let result = Reader::new(&mut legal, pgn).read_game();
+/// assert_eq!(result, Some((2, 1)));
+/// # }
+/// ```
+///
+/// [`LegalVisitor`]: trait.LegalVisitor.html
+/// [`Visitor`]: trait.Visitor.html
+/// [`play`]: trait.LegalVisitor.html#method.play
+/// [`null_move`]: trait.LegalVisitor.html#method.null_move
+/// [`illegal_move`]: trait.LegalVisitor.html#method.illegal_move
+#[derive(Debug)]
+pub struct Legal<V> {
+    inner: V,
+    frame: Frame,
+    stack: Vec<Frame>,
+}
+
+impl<V> Legal<V> {
+    /// Wraps a visitor.
+    pub fn new(inner: V) -> Legal<V> {
+        Legal { inner, frame: Frame::default(), stack: Vec::new() }
+    }
+
+    /// Gets a reference to the inner visitor.
+    pub fn get_ref(&self) -> &V {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the inner visitor.
+    pub fn get_mut(&mut self) -> &mut V {
+        &mut self.inner
+    }
+
+    /// Unwraps the inner visitor.
+    pub fn into_inner(self) -> V {
+        self.inner
+    }
+}
+
+impl<'pgn, V: LegalVisitor<'pgn>> Legal<V> {
+    fn fail(&mut self, error: LegalError) {
+        self.frame.failed = true;
+        self.inner.illegal_move(error, &self.frame.pos);
+    }
+
+    fn play_move(&mut self, m: Result<Move, LegalError>) {
+        match m {
+            Ok(m) => {
+                let before = self.frame.pos.clone();
+                self.frame.pos.play_unchecked(&m);
+                self.inner.play(&before, &m, &self.frame.pos);
+                self.frame.before = Some(before);
+            },
+            Err(error) => {
+                self.frame.failed_last = true;
+                self.fail(error);
+            },
+        }
+    }
+
+    fn play_null(&mut self) {
+        match self.frame.pos.clone().swap_turn() {
+            Ok(after) => {
+                let before = mem::replace(&mut self.frame.pos, after);
+                self.inner.null_move(&before, &self.frame.pos);
+                self.frame.before = Some(before);
+            },
+            Err(_) => {
+                self.frame.failed_last = true;
+                self.fail(LegalError::IllegalNullMove);
+            },
+        }
+    }
+}
+
+impl<'pgn, V: LegalVisitor<'pgn>> Visitor<'pgn> for Legal<V> {
+    type Result = V::Result;
+
+    fn begin_game(&mut self) {
+        self.frame = Frame::default();
+        self.stack.clear();
+        self.inner.begin_game();
+    }
+
+    fn begin_headers(&mut self) {
+        self.inner.begin_headers();
+    }
+
+    fn header(&mut self, key: &'pgn [u8], value: RawHeader<'pgn>) {
+        self.inner.header(key, value);
+
+        if key == b"FEN" {
+            match Fen::from_bytes(value.as_bytes()).map(|fen| fen.position()) {
+                Ok(Ok(pos)) => {
+                    self.frame.pos = pos;
+                    self.frame.failed = false;
+                },
+                Ok(Err(_)) => self.fail(LegalError::IllegalPosition),
+                Err(_) => self.fail(LegalError::InvalidFen),
+            }
+        }
+    }
+
+    fn end_headers(&mut self) -> Skip {
+        self.inner.end_headers()
+    }
+
+    fn move_number(&mut self, number: u32, color: Color) {
+        self.inner.move_number(number, color);
+    }
+
+    fn san(&mut self, san_plus: SanPlus) {
+        self.frame.failed_last = false;
+        let m = if self.frame.failed || san_plus.san == San::Null {
+            None
+        } else {
+            Some(san_plus.san.to_move(&self.frame.pos).map_err(|err| match err {
+                SanError::IllegalSan => LegalError::IllegalMove,
+                SanError::AmbiguousSan => LegalError::AmbiguousMove,
+            }))
+        };
+        let null = san_plus.san == San::Null;
+
+        self.inner.san(san_plus);
+
+        if let Some(m) = m {
+            self.play_move(m);
+        } else if null && !self.frame.failed {
+            self.play_null();
+        }
+    }
+
+    fn uci(&mut self, uci: Uci) {
+        self.frame.failed_last = false;
+        let m = if self.frame.failed {
+            None
+        } else {
+            Some(uci.to_move(&self.frame.pos).map_err(|_| LegalError::IllegalMove))
+        };
+
+        self.inner.uci(uci);
+
+        if let Some(m) = m {
+            self.play_move(m);
+        }
+    }
+
+    fn nag(&mut self, nag: Nag) {
+        self.inner.nag(nag);
+    }
+
+    fn comment(&mut self, comment: &'pgn [u8]) {
+        self.inner.comment(comment);
+    }
+
+    fn line_comment(&mut self, comment: &'pgn [u8]) {
+        self.inner.line_comment(comment);
+    }
+
+    fn escape(&mut self, line: &'pgn [u8]) {
+        self.inner.escape(line);
+    }
+
+    fn begin_variation(&mut self) -> Skip {
+        // The variation replaces the last move. Without a preceding move, or
+        // if the last move could not be played, it starts from the current
+        // position.
+        let variation = if self.frame.failed_last {
+            Frame { pos: self.frame.pos.clone(), ..Frame::default() }
+        } else {
+            Frame {
+                pos: self.frame.before.clone().unwrap_or_else(|| self.frame.pos.clone()),
+                before: None,
+                failed: self.frame.failed,
+                failed_last: false,
+            }
+        };
+        self.stack.push(mem::replace(&mut self.frame, variation));
+        self.inner.begin_variation()
+    }
+
+    fn end_variation(&mut self) {
+        if let Some(frame) = self.stack.pop() {
+            self.frame = frame;
+        }
+        self.inner.end_variation();
+    }
+
+    fn outcome(&mut self, outcome: Option<Outcome>) {
+        self.inner.outcome(outcome);
+    }
+
+    fn skip_movetext(&mut self) -> Skip {
+        self.inner.skip_movetext()
+    }
+
+    fn syntax_error(&mut self, error: SyntaxError, token: &'pgn [u8]) {
+        self.inner.syntax_error(error, token);
+    }
+
+    fn span(&mut self, span: Span) {
+        self.inner.span(span);
+    }
+
+    fn end_game(&mut self, game: &'pgn [u8]) -> Self::Result {
+        self.inner.end_game(game)
+    }
+
+    fn stop(&mut self) -> Stop {
+        self.inner.stop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Reader;
+
+    #[derive(Default)]
+    struct Trace {
+        events: Vec<String>,
+    }
+
+    impl<'pgn> Visitor<'pgn> for Trace {
+        type Result = Vec<String>;
+
+        fn end_game(&mut self, _game: &'pgn [u8]) -> Self::Result {
+            mem::take(&mut self.events)
+        }
+    }
+
+    impl<'pgn> LegalVisitor<'pgn> for Trace {
+        fn play(&mut self, before: &Chess, m: &Move, _after: &Chess) {
+            self.events.push(::shakmaty::uci::uci(before, m).to_string());
+        }
+
+        fn null_move(&mut self, _before: &Chess, _after: &Chess) {
+            self.events.push("0000".to_owned());
+        }
+
+        fn illegal_move(&mut self, error: LegalError, _pos: &Chess) {
+            self.events.push(error.to_string());
+        }
+    }
+
+    fn trace(pgn: &[u8]) -> Vec<String> {
+        let mut legal = Legal::new(Trace::default());
+        Reader::new(&mut legal, pgn).read_game().expect("game")
+    }
+
+    #[test]
+    fn test_variations() {
+        assert_eq!(trace(b"1. e4 e5 (1... c5 2. Nf3 (2. Nc3) d6) 2. Nf3 *"),
+                   vec!["e2e4", "e7e5", "c7c5", "g1f3", "b1c3", "d7d6", "g1f3"]);
+
+        // An error only ends its own line.
+        assert_eq!(trace(b"1. e4 (1. e5 d4) 1... e5 2. Nd3 Nc6 (2... Nf6) 3. Nc3 *"),
+                   vec!["e2e4", "illegal move", "e7e5", "illegal move"]);
+
+        // Variations of the failed move itself are resolved.
+        assert_eq!(trace(b"1. e4 e5 2. Nd3 (2. Nf3 Nc6) (2. Nc3) Nc6 (2... Nf6) *"),
+                   vec!["e2e4", "e7e5", "illegal move", "g1f3", "b8c6", "b1c3"]);
+        assert_eq!(trace(b"1. e4 e5 2. Nd3 ({ better } 2. Nf3 -- 3. Bc4) *"),
+                   vec!["e2e4", "e7e5", "illegal move", "g1f3", "0000", "f1c4"]);
+    }
+
+    #[test]
+    fn test_fen_and_null_moves() {
+        let pgn = b"[FEN \"4k3/8/8/8/8/8/8/R3K3 w Q - 0 1\"]\n\n1. O-O-O -- 2. Rd8+ -- *";
+        assert_eq!(trace(pgn), vec!["e1c1", "0000", "d1d8", "illegal null move"]);
+
+        assert_eq!(trace(b"[FEN \"8/8/8/8/8/8/8/8 w - - 0 1\"]\n\n1. e4 *"), vec!["illegal position"]);
+        assert_eq!(trace(b"[FEN \"foo\"]\n\n1. e4 *"), vec!["invalid fen"]);
+    }
+
+    #[test]
+    fn test_ambiguous() {
+        let pgn = b"[FEN \"4k3/8/8/8/8/8/4K3/R6R w - - 0 1\"]\n\n1. Rd1 *";
+        assert_eq!(trace(pgn), vec!["ambiguous move"]);
+
+        assert_eq!(trace(b"1. e2-e4 e7e5 2. Ng1-f3 Ke8-e6 *"),
+                   vec!["e2e4", "e7e5", "g1f3", "illegal move"]);
+
+        // The piece letter of long algebraic notation must match.
+        assert_eq!(trace(b"1. Bg1-f3 *"), vec!["illegal move"]);
+        assert_eq!(trace(b"1. Ng1xf3 *"), vec!["illegal move"]);
+        assert_eq!(trace(b"1. e2xe4 *"), vec!["illegal move"]);
+    }
+}
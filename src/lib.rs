@@ -26,6 +26,23 @@
 //! * The visitor can signal to the reader that it does not care about a game
 //!   or variation.
 //!
+//! [`BufferedReader`] does the same for streams like stdin or pipes, where the
+//! whole PGN is not available as a byte slice. [`ParallelReader`] splits a
+//! large byte slice at game boundaries and parses the parts on multiple
+//! threads.
+//!
+//! [`GameBuilder`] is a visitor that collects each game into an owned
+//! [`Game`] with headers, variations, comments and NAGs. [`GameRefBuilder`]
+//! does the same without copying, borrowing from the input.
+//! [`ExportValidator`] is a visitor that checks games against the stricter
+//! PGN export format. [`IndexBuilder`] records the offsets of games for
+//! random access into large files. [`Comment`] parses commands like
+//! `[%clk 0:03:00]` embedded in comments. [`Headers`] collects the headers
+//! of each game and parses standard values like partial dates, rounds and
+//! time controls.
+//! [`Legal`] wraps a visitor and plays the moves of each game, including
+//! variations, reporting the positions and the first illegal move.
+//!
 //! # Flow
 //!
 //! Visitor methods are called in this order:
@@ -40,7 +57,7 @@
 //! ```
 //! extern crate pgn_reader;
 //!
-//! use pgn_reader::{Visitor, Skip, Reader, San};
+//! use pgn_reader::{Visitor, Skip, Reader, SanPlus};
 //!
 //! struct MoveCounter {
 //!     moves: usize,
@@ -59,7 +76,7 @@
 //!         self.moves = 0;
 //!     }
 //!
-//!     fn san(&mut self, _san: San) {
+//!     fn san(&mut self, _san_plus: SanPlus) {
 //!         self.moves += 1;
 //!     }
 //!
@@ -91,7 +108,7 @@
 //! extern crate pgn_reader;
 //! extern crate shakmaty;
 //!
-//! use pgn_reader::{Visitor, Skip, Reader, San};
+//! use pgn_reader::{Visitor, Skip, Reader, RawHeader, SanPlus};
 //!
 //! use shakmaty::{Chess, Position};
 //! use shakmaty::fen::Fen;
@@ -109,10 +126,10 @@
 //! impl<'pgn> Visitor<'pgn> for LastPosition {
 //!     type Result = Chess;
 //!
-//!     fn header(&mut self, key: &'pgn [u8], value: &'pgn [u8]) {
+//!     fn header(&mut self, key: &'pgn [u8], value: RawHeader<'pgn>) {
 //!         // Support games from a non-standard starting position.
 //!         if key == b"FEN" {
-//!             let pos = Fen::from_bytes(value).ok()
+//!             let pos = Fen::from_bytes(value.as_bytes()).ok()
 //!                 .and_then(|f| f.position().ok());
 //!
 //!             if let Some(pos) = pos {
@@ -125,8 +142,8 @@
 //!         Skip(true) // stay in the mainline
 //!     }
 //!
-//!     fn san(&mut self, san: San) {
-//!         if let Ok(m) = san.to_move(&self.pos) {
+//!     fn san(&mut self, san_plus: SanPlus) {
+//!         if let Ok(m) = san_plus.san.to_move(&self.pos) {
 //!             self.pos.play_unchecked(&m);
 //!         }
 //!     }
@@ -148,6 +165,16 @@
 //! ```
 //!
 //! [`Reader`]: struct.Reader.html
+//! [`BufferedReader`]: struct.BufferedReader.html
+//! [`ParallelReader`]: struct.ParallelReader.html
+//! [`GameBuilder`]: struct.GameBuilder.html
+//! [`Game`]: struct.Game.html
+//! [`GameRefBuilder`]: struct.GameRefBuilder.html
+//! [`ExportValidator`]: struct.ExportValidator.html
+//! [`IndexBuilder`]: struct.IndexBuilder.html
+//! [`Comment`]: struct.Comment.html
+//! [`Headers`]: struct.Headers.html
+//! [`Legal`]: struct.Legal.html
 //! [`Visitor`]: trait.Visitor.html
 //! [Shakmaty]: ../shakmaty/index.html
 
@@ -159,21 +186,70 @@ extern crate memchr;
 extern crate btoi;
 extern crate shakmaty;
 
+#[cfg(feature = "decompress")]
+extern crate flate2;
+#[cfg(feature = "decompress")]
+extern crate bzip2;
+#[cfg(feature = "decompress")]
+extern crate zstd;
+#[cfg(feature = "decompress")]
+extern crate xz2;
+#[cfg(feature = "async")]
+extern crate tokio;
+#[cfg(feature = "async")]
+extern crate futures_core;
+
 use std::fmt;
-use std::cmp::max;
-use std::str::FromStr;
+use std::str::{self, FromStr, Utf8Error};
 use std::error::Error;
+use std::borrow::Cow;
 
-pub use shakmaty::san::San;
+pub use shakmaty::san::{San, SanPlus};
+pub use shakmaty::uci::Uci;
 pub use shakmaty::{Color, CastlingSide, Outcome, Role, Square};
 
 use btoi::btou;
 
+mod buffered;
+mod comment;
+mod game;
+mod game_ref;
+mod headers;
+mod index;
+mod legal;
+mod notation;
+mod parallel;
+mod validate;
+#[cfg(feature = "decompress")]
+mod decompress;
+#[cfg(feature = "async")]
+mod async_reader;
+
+pub use buffered::{BufferedReader, BufferedIter};
+pub use comment::{Comment, Command, Eval, AnnotationColor, Highlight, Arrow};
+pub use game::{Game, GameBuilder, Line, Node, PgnMove, NodePath, Cursor};
+pub use game_ref::{GameRef, GameRefBuilder, NodeRef, Variations};
+pub use headers::{Headers, Date, Time, Round, TimeControl, TimePeriod, Termination, Variant, InvalidHeaderValue};
+pub use index::{IndexBuilder, Index, Entry, Filter};
+pub use legal::{Legal, LegalVisitor, LegalError};
+pub use notation::Notation;
+pub use parallel::ParallelReader;
+pub use validate::{ExportValidator, Violation, ViolationKind};
+#[cfg(feature = "decompress")]
+pub use decompress::{Compression, Decompressor};
+#[cfg(feature = "async")]
+pub use async_reader::{AsyncReader, ReadGame};
+
 /// Tell the reader to skip over a game.
 #[derive(Clone, Eq, PartialEq, Debug)]
 #[must_use]
 pub struct Skip(pub bool);
 
+/// Tell the reader to stop reading after the current game.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[must_use]
+pub struct Stop(pub bool);
+
 /// A numeric annotation glyph like `?`, `!!` or `$42`.
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
 pub struct Nag(pub u8);
@@ -262,6 +338,323 @@ impl FromStr for Nag {
     }
 }
 
+/// A problem with a token in the headers or movetext, that the reader skipped
+/// over.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum SyntaxError {
+    /// A token that looks like a move, but is not valid SAN.
+    InvalidSan,
+    /// A `!`, `?` or `$` that does not start a valid NAG.
+    InvalidNag,
+    /// Any other unexpected token, like `+` or `1/2`.
+    UnknownToken,
+    /// A `}` that does not close a comment.
+    UnmatchedBrace,
+    /// A `)` that does not close a variation.
+    UnmatchedParenthesis,
+    /// A `{` comment that is still open at a game boundary, when recovering
+    /// (see [`Reader::recover`]).
+    ///
+    /// [`Reader::recover`]: struct.Reader.html#method.recover
+    UnterminatedComment,
+    /// A header line that is not a valid tag pair, like `[Event]` or
+    /// `[Event "?"` without the closing bracket.
+    InvalidHeader,
+}
+
+impl SyntaxError {
+    fn desc(&self) -> &str {
+        match *self {
+            SyntaxError::InvalidSan => "invalid san",
+            SyntaxError::InvalidNag => "invalid nag",
+            SyntaxError::UnknownToken => "unknown token",
+            SyntaxError::UnmatchedBrace => "unmatched brace",
+            SyntaxError::UnmatchedParenthesis => "unmatched parenthesis",
+            SyntaxError::UnterminatedComment => "unterminated comment",
+            SyntaxError::InvalidHeader => "invalid header",
+        }
+    }
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.desc().fmt(f)
+    }
+}
+
+impl Error for SyntaxError {
+    fn description(&self) -> &str {
+        self.desc()
+    }
+}
+
+/// Error when parsing an invalid `Result` header.
+#[derive(Clone, Eq, PartialEq)]
+pub struct InvalidResult {
+    _priv: (),
+}
+
+impl fmt::Debug for InvalidResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("InvalidResult").finish()
+    }
+}
+
+impl fmt::Display for InvalidResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        "invalid result".fmt(f)
+    }
+}
+
+impl Error for InvalidResult {
+    fn description(&self) -> &str {
+        "invalid result"
+    }
+}
+
+/// Error when the `Result` header does not match the game termination
+/// marker.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct ResultMismatch<'pgn> {
+    /// The value of the `Result` header.
+    pub header: RawHeader<'pgn>,
+    /// The game termination marker, or `None` for `*`.
+    pub marker: Option<Outcome>,
+}
+
+impl<'pgn> fmt::Display for ResultMismatch<'pgn> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "result header {:?} does not match game termination marker ", String::from_utf8_lossy(self.header.0))?;
+        match self.marker {
+            Some(ref outcome) => outcome.fmt(f),
+            None => "*".fmt(f),
+        }
+    }
+}
+
+impl<'pgn> Error for ResultMismatch<'pgn> {
+    fn description(&self) -> &str {
+        "result mismatch"
+    }
+}
+
+/// A header value as it appears in the PGN, possibly containing the escape
+/// sequences `\"` and `\\`.
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+pub struct RawHeader<'pgn>(pub &'pgn [u8]);
+
+impl<'pgn> RawHeader<'pgn> {
+    /// Gets the raw bytes, without unescaping.
+    pub fn as_bytes(&self) -> &'pgn [u8] {
+        self.0
+    }
+
+    /// Unescapes the value. Borrows from the input if there are no escape
+    /// sequences.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pgn_reader::RawHeader;
+    ///
+    /// let value = RawHeader(br#"The \"Fischer\" Match"#);
+    /// assert_eq!(value.decode(), &b"The \"Fischer\" Match"[..]);
+    /// ```
+    pub fn decode(&self) -> Cow<'pgn, [u8]> {
+        let mut head = 0;
+        let mut decoded = Vec::new();
+        for escape in memchr::memchr_iter(b'\\', self.0) {
+            if escape < head {
+                continue; // Second backslash of an escaped backslash
+            }
+            match self.0.get(escape + 1) {
+                Some(&ch) if ch == b'\\' || ch == b'"' => {
+                    decoded.extend_from_slice(&self.0[head..escape]);
+                    decoded.push(ch);
+                    head = escape + 2;
+                },
+                _ => (),
+            }
+        }
+
+        if head == 0 {
+            Cow::Borrowed(self.0)
+        } else {
+            decoded.extend_from_slice(&self.0[head..]);
+            Cow::Owned(decoded)
+        }
+    }
+
+    /// Unescapes the value and checks that it is valid UTF-8.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the value is not valid UTF-8.
+    pub fn decode_utf8(&self) -> Result<Cow<'pgn, str>, Utf8Error> {
+        match self.decode() {
+            Cow::Borrowed(value) => str::from_utf8(value).map(Cow::Borrowed),
+            Cow::Owned(value) => String::from_utf8(value).map(Cow::Owned).map_err(|err| err.utf8_error()),
+        }
+    }
+
+    /// Unescapes the value, replacing invalid UTF-8 sequences with
+    /// `U+FFFD REPLACEMENT CHARACTER`.
+    pub fn decode_utf8_lossy(&self) -> Cow<'pgn, str> {
+        match self.decode() {
+            Cow::Borrowed(value) => String::from_utf8_lossy(value),
+            Cow::Owned(value) => Cow::Owned(String::from_utf8_lossy(&value).into_owned()),
+        }
+    }
+
+    /// Parses the value of a `Result` header. `*` is an unknown result.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`InvalidResult`] if the value is not one of `1-0`, `0-1`,
+    /// `1/2-1/2` or `*`.
+    ///
+    /// [`InvalidResult`]: struct.InvalidResult.html
+    pub fn outcome(&self) -> Result<Option<Outcome>, InvalidResult> {
+        match self.0 {
+            b"1-0" => Ok(Some(Outcome::Decisive { winner: Color::White })),
+            b"0-1" => Ok(Some(Outcome::Decisive { winner: Color::Black })),
+            b"1/2-1/2" => Ok(Some(Outcome::Draw)),
+            b"*" => Ok(None),
+            _ => Err(InvalidResult { _priv: () }),
+        }
+    }
+
+    /// Checks the value of a `Result` header against the game termination
+    /// marker, as passed to [`Visitor::outcome`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ResultMismatch`] if the header is invalid or does not match
+    /// the marker.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pgn_reader::{RawHeader, Outcome, Color};
+    ///
+    /// let header = RawHeader(b"1-0");
+    /// assert!(header.check_outcome(Some(Outcome::Decisive { winner: Color::White })).is_ok());
+    /// assert!(header.check_outcome(None).is_err());
+    /// assert!(RawHeader(b"*").check_outcome(None).is_ok());
+    /// ```
+    ///
+    /// [`Visitor::outcome`]: trait.Visitor.html#method.outcome
+    /// [`ResultMismatch`]: struct.ResultMismatch.html
+    pub fn check_outcome(&self, marker: Option<Outcome>) -> Result<(), ResultMismatch<'pgn>> {
+        match self.outcome() {
+            Ok(ref header) if *header == marker => Ok(()),
+            _ => Err(ResultMismatch { header: *self, marker }),
+        }
+    }
+}
+
+impl<'pgn> fmt::Debug for RawHeader<'pgn> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "RawHeader({:?})", String::from_utf8_lossy(self.0))
+    }
+}
+
+/// The location of a token as a range of byte offsets into the input,
+/// along with the line and column where it starts.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{Reader, Visitor, Span, Location};
+///
+/// struct Spans(Vec<Span>);
+///
+/// impl<'pgn> Visitor<'pgn> for Spans {
+///     type Result = Vec<Span>;
+///
+///     fn span(&mut self, span: Span) {
+///         self.0.push(span);
+///     }
+///
+///     fn end_game(&mut self, _game: &'pgn [u8]) -> Vec<Span> {
+///         ::std::mem::replace(&mut self.0, Vec::new())
+///     }
+/// }
+///
+/// let pgn = b"[Event \"?\"]\n\n1. e4 e5";
+///
+/// let spans = Reader::new(&mut Spans(Vec::new()), pgn).read_game().unwrap();
+/// let e5 = spans[3];
+/// assert_eq!(&pgn[e5.start..e5.end], b"e5");
+/// assert_eq!(e5.location, Location { line: 3, column: 7 });
+/// ```
+#[derive(Copy, Clone, Default, Eq, PartialEq, Hash, Debug)]
+pub struct Span {
+    /// Offset of the first byte of the token.
+    pub start: usize,
+    /// Offset directly after the last byte of the token.
+    pub end: usize,
+    /// Line and column of the first byte of the token.
+    pub location: Location,
+}
+
+/// A line and column (counted in bytes), both starting at 1.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Default for Location {
+    /// The start of the input.
+    fn default() -> Location {
+        Location { line: 1, column: 1 }
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// Counts lines incrementally, to find the location of a byte offset
+/// without rescanning the input.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct LineCounter {
+    /// Offset up to which lines have been counted.
+    pub pos: usize,
+    line: usize,
+    line_start: usize,
+}
+
+impl Default for LineCounter {
+    fn default() -> LineCounter {
+        LineCounter::new(0, Location::default())
+    }
+}
+
+impl LineCounter {
+    /// Creates a counter at `pos`, which is at `location`.
+    pub fn new(pos: usize, location: Location) -> LineCounter {
+        LineCounter { pos, line: location.line, line_start: (pos + 1).saturating_sub(location.column) }
+    }
+
+    /// Counts the lines of `text`, which starts at the current offset.
+    pub fn advance(&mut self, text: &[u8]) {
+        if let Some(last) = memchr::memrchr(b'\n', text) {
+            self.line += memchr::memchr_iter(b'\n', text).count();
+            self.line_start = self.pos + last + 1;
+        }
+        self.pos += text.len();
+    }
+
+    /// Gets the location of the current offset.
+    pub fn location(&self) -> Location {
+        Location { line: self.line, column: self.pos - self.line_start + 1 }
+    }
+}
+
 /// Consumes games from a reader.
 ///
 /// ![Flow](https://github.com/niklasf/rust-pgn-reader/blob/master/docs/visitor.png?raw=true)
@@ -274,37 +667,107 @@ pub trait Visitor<'pgn> {
     /// Called directly before reading game headers.
     fn begin_headers(&mut self) { }
     /// Called when parsing a game header like `[White "Deep Blue"]`.
-    fn header(&mut self, _key: &'pgn [u8], _value: &'pgn [u8]) { }
+    fn header(&mut self, _key: &'pgn [u8], _value: RawHeader<'pgn>) { }
     /// Called after reading the headers of a game. May skip quickly over the
     /// following move text directly to `end_game`.
     fn end_headers(&mut self) -> Skip { Skip(false) }
 
-    /// Called for each move, like `Nf3`.
-    fn san(&mut self, _san: San) { }
+    /// Called for each move number indication, like `12.` (followed by a
+    /// move of white) or `12...` (followed by a move of black). A number
+    /// without any periods, like `12`, is taken as a move of white.
+    fn move_number(&mut self, _number: u32, _color: Color) { }
+    /// Called for each move, like `Nf3` or `Qh4#`, including check and
+    /// checkmate suffixes as written. Null moves like `--` or `Z0` are
+    /// reported as `San::Null`. Moves in long algebraic notation, like
+    /// `Ng1-f3`, `Nb8xc6` or `e2-e4`, are reported as fully disambiguated
+    /// SAN.
+    fn san(&mut self, _san_plus: SanPlus) { }
+    /// Called for each move in coordinate notation, like `e2e4` or `e7e8q`.
+    /// Check and checkmate suffixes are ignored.
+    fn uci(&mut self, _uci: Uci) { }
     /// Called for each numeric annotation glyph, like `!?` or `$7`.
     fn nag(&mut self, _nag: Nag) { }
     /// Called for each `{ comment }` with the whole comment as a byte slice,
     /// excluding the braces.
     fn comment(&mut self, _comment: &'pgn [u8]) { }
+    /// Called for each rest-of-line comment, like `; comment`, with the text
+    /// after the `;`, excluding the line break.
+    fn line_comment(&mut self, _comment: &'pgn [u8]) { }
+    /// Called for each escape line, like `%metadata`, in the headers or the
+    /// move text, with the text after the `%`, excluding the line break.
+    /// Escape lines between games are reported with the preceding game.
+    fn escape(&mut self, _line: &'pgn [u8]) { }
     /// Called for each `(`. May skip over the following variation directly
     /// to `end_variation` (or to `end_game` if no `)` follows before the end of
     /// the game).
     fn begin_variation(&mut self) -> Skip { Skip(false) }
-    /// Called for each `)`. It is **not** guaranteed that there was a
-    /// matching `(`.
+    /// Called for each `)` that closes a variation.
     fn end_variation(&mut self) { }
-    /// Called for each game termination, like `1-0`.
-    fn outcome(&mut self, _outcome: Outcome) { }
+    /// Called for each game termination marker, like `1-0`, or with `None`
+    /// for `*` (game in progress, result unknown or game abandoned).
+    fn outcome(&mut self, _outcome: Option<Outcome>) { }
+
+    /// Called after each `san`, `uci`, `nag` and `comment`. May skip quickly over
+    /// the rest of the move text directly to `end_game`. Variations that are
+    /// still open are closed with `end_variation` first.
+    fn skip_movetext(&mut self) -> Skip { Skip(false) }
+
+    /// Called for each token that the reader does not understand and skips
+    /// over, like `Nf9`, an unmatched `)` or a malformed header line.
+    fn syntax_error(&mut self, _error: SyntaxError, _token: &'pgn [u8]) { }
+
+    /// Called directly before `header`, `move_number`, `san`, `uci`, `nag`,
+    /// `comment`, `line_comment`, `escape`, `begin_variation`,
+    /// `end_variation`, `outcome` and `syntax_error` with
+    /// the location of the respective token, and before `end_game` with the
+    /// location of the whole game.
+    fn span(&mut self, _span: Span) { }
 
     /// Called after parsing a game. Can return a custom result.
     fn end_game(&mut self, game: &'pgn [u8]) -> Self::Result;
+
+    /// Called after `end_game`. May stop reading, so that no further games
+    /// are read.
+    fn stop(&mut self) -> Stop { Stop(false) }
+}
+
+/// A move in the movetext.
+enum MoveToken {
+    /// A move in SAN, or a move in long algebraic notation, like `Ng1-f3`
+    /// or `e2-e4`.
+    San(SanPlus),
+    /// A move in coordinate notation, like `e2e4`.
+    Uci(Uci),
+}
+
+/// Tests for the different ways null moves are written, like `--` or `Z0`.
+fn is_null_move(token: &[u8]) -> bool {
+    matches!(token, b"--" | b"Z0" | b"0000" | b"@@@@")
 }
 
 fn is_space(b: u8) -> bool {
-    match b {
-        b' ' | b'\t' | b'\n' | b'\r' => true,
-        _ => false
-    }
+    matches!(b, b' ' | b'\t' | b'\n' | b'\r')
+}
+
+/// Finds the position of a line break directly followed by an empty line
+/// and `[Event`, where a new game most likely starts.
+fn find_boundary(pgn: &[u8]) -> Option<usize> {
+    memchr::memchr_iter(b'[', pgn).find_map(|pos| {
+        if !pgn[pos..].starts_with(b"[Event") {
+            None
+        } else if pgn[..pos].ends_with(b"\n\n") {
+            Some(pos - 2)
+        } else if pgn[..pos].ends_with(b"\n\r\n") {
+            Some(pos - 3)
+        } else {
+            None
+        }
+    })
+}
+
+/// Excludes a `\r` directly before `end`, as long as `end > start`.
+fn trim_cr(pgn: &[u8], start: usize, end: usize) -> usize {
+    if end > start && pgn[end - 1] == b'\r' { end - 1 } else { end }
 }
 
 fn split_after_pgn_space(pgn: &[u8], mut pos: usize) -> (&[u8], &[u8]) {
@@ -330,6 +793,13 @@ fn split_after_pgn_space(pgn: &[u8], mut pos: usize) -> (&[u8], &[u8]) {
 pub struct Reader<'a, 'pgn, V: Visitor<'pgn>> where V: 'a {
     visitor: &'a mut V,
     pgn: &'pgn[u8],
+    offset: usize,
+    origin: LineCounter,
+    lines: LineCounter,
+    stopped: bool,
+    skipped: bool,
+    recover: bool,
+    notation: Option<Notation>,
 }
 
 impl<'a, 'pgn, V: Visitor<'pgn>> fmt::Debug for Reader<'a, 'pgn, V> {
@@ -343,18 +813,62 @@ impl<'a, 'pgn, V: Visitor<'pgn>> Reader<'a, 'pgn, V> {
     ///
     /// [`Visitor`]: trait.Visitor.html
     pub fn new(visitor: &'a mut V, pgn: &'pgn[u8]) -> Reader<'a, 'pgn, V> {
+        Reader::with_offset(visitor, pgn, LineCounter::default())
+    }
+
+    /// Creates a reader for a part of the input that starts at the position
+    /// of `lines`.
+    fn with_offset(visitor: &'a mut V, pgn: &'pgn[u8], mut lines: LineCounter) -> Reader<'a, 'pgn, V> {
         // Skip BOM.
         let pos = if pgn.starts_with(b"\xef\xbb\xbf") { 3 } else { 0 };
 
         // Skip leading whitespace.
-        let (_, pgn) = split_after_pgn_space(pgn, pos);
-        Reader { visitor, pgn }
+        let (head, pgn) = split_after_pgn_space(pgn, pos);
+        lines.advance(head);
+        Reader {
+            visitor,
+            pgn,
+            offset: lines.pos,
+            origin: lines,
+            lines,
+            stopped: false,
+            skipped: false,
+            recover: false,
+            notation: None,
+        }
+    }
+
+    /// Sets the piece letters used in the movetext. Defaults to standard
+    /// English notation.
+    pub fn notation(&mut self, notation: Notation) -> &mut Reader<'a, 'pgn, V> {
+        self.notation = if notation.is_english() { None } else { Some(notation) };
+        self
+    }
+
+    /// Enables recovery from runaway comments and header lines. Disabled by
+    /// default.
+    ///
+    /// When recovering, a `{` comment that is still open at a game boundary
+    /// (an empty line followed by `[Event`) ends there, instead of swallowing
+    /// the following games up to the next `}`. The comment is reported as
+    /// [`SyntaxError::UnterminatedComment`]. A header line with a quoted
+    /// value but without the closing `]` is still passed to `header`.
+    ///
+    /// [`SyntaxError::UnterminatedComment`]: enum.SyntaxError.html#variant.UnterminatedComment
+    pub fn recover(&mut self, recover: bool) -> &mut Reader<'a, 'pgn, V> {
+        self.recover = recover;
+        self
     }
 
     /// Read the next game, returning the result from the visitor, or `None`
-    /// if there was no further game.
+    /// if there was no further game or the visitor requested to stop.
     pub fn read_game(&mut self) -> Option<V::Result> {
+        if self.stopped {
+            return None;
+        }
+
         // Scan game.
+        self.skipped = false;
         self.visitor.begin_game();
         self.visitor.begin_headers();
         let pos = self.scan_headers();
@@ -366,68 +880,93 @@ impl<'a, 'pgn, V: Visitor<'pgn>> Reader<'a, 'pgn, V> {
 
         // Skip trailing whitespace.
         let (head, tail) = split_after_pgn_space(self.pgn, pos);
+        if !self.skipped {
+            self.scan_escape_lines(pos, head.len());
+        }
+        self.lines.advance(&head[self.lines.pos - self.offset..]);
+        self.visitor.span(Span { start: self.offset, end: self.lines.pos, location: self.origin.location() });
         self.pgn = tail;
+        self.offset = self.lines.pos;
+        self.origin = self.lines;
 
         // Check for any content.
         if head.iter().all(|c| is_space(*c)) {
             self.visitor.end_game(head);
             None
         } else {
-            Some(self.visitor.end_game(head))
+            let result = self.visitor.end_game(head);
+            self.stopped = self.visitor.stop().0;
+            Some(result)
         }
     }
 
-    /// Reads all games.
-    pub fn read_all(mut self) {
-        while let Some(_) = self.read_game() { }
+    /// Reads all games, or until the visitor requests to stop.
+    pub fn read_all(&mut self) {
+        while self.read_game().is_some() { }
+    }
+
+    /// Tests if reading was stopped by the visitor.
+    pub fn stopped(&self) -> bool {
+        self.stopped
+    }
+
+    /// Gets the byte offset of the next game in the input. After reading
+    /// was stopped, this is where reading could be resumed.
+    pub fn position(&self) -> usize {
+        self.offset
+    }
+
+    /// Gets the line and column of the next game in the input.
+    pub(crate) fn origin(&self) -> LineCounter {
+        self.origin
+    }
+
+    fn span(&mut self, start: usize, end: usize) {
+        // Tokens are reported in order, so lines only need to be counted
+        // from the previous token, unless going back to an earlier token.
+        if self.offset + start < self.lines.pos {
+            self.lines = self.origin;
+        }
+        let from = self.lines.pos - self.offset;
+        self.lines.advance(&self.pgn[from..start]);
+
+        let offset = self.offset;
+        let location = self.lines.location();
+        self.visitor.span(Span { start: offset + start, end: offset + end, location });
     }
 
     fn scan_headers(&mut self) -> usize {
         let mut pos = 0;
+        let mut after_tag_pair = false;
 
         while pos < self.pgn.len() {
             match self.pgn[pos] {
                 b'[' => {
+                    pos = self.scan_tag_pair(pos);
+                    after_tag_pair = true;
+                },
+                b' ' | b'\t' | b'\r' => {
                     pos += 1;
-                    let key_pos = pos;
-                    match memchr::memchr2(b'"', b'\n', &self.pgn[pos..]) {
-                        Some(delta) if self.pgn[pos + delta] == b'"' => {
-                            pos += delta;
-                            let key_end_pos = if self.pgn[pos - 1] == b' ' {
-                                pos - 1
-                            } else {
-                                pos
-                            };
-                            pos += 1;
-                            let value_pos = pos;
-
-                            pos = memchr::memchr(b'\n', &self.pgn[pos..]).map_or_else(|| self.pgn.len(), |p| pos + p + 1);
-                            let eol_pos = if self.pgn[pos - 1] == b'\n' {
-                                if self.pgn[pos - 2] == b'\r' {
-                                    pos - 2
-                                } else {
-                                    pos - 1
-                                }
-                            } else {
-                                continue;
-                            };
-
-                            let value_end_pos = if self.pgn[eol_pos - 1] == b']' && self.pgn[eol_pos - 2] == b'"' {
-                                max(value_pos + 1, eol_pos - 2)
-                            } else {
-                                continue;
-                            };
-
-                            self.visitor.header(&self.pgn[key_pos..key_end_pos],
-                                                &self.pgn[value_pos..value_end_pos]);
-                        },
-                        Some(delta) => pos += delta + 1,
-                        None => pos = self.pgn.len(),
+                },
+                b'\n' => {
+                    // Stop at the first empty line.
+                    pos += 1;
+                    after_tag_pair = false;
+                    if self.pgn[pos..].starts_with(b"\n") || self.pgn[pos..].starts_with(b"\r\n") {
+                        break;
                     }
                 },
                 b'%' => {
-                    pos += 1;
-                    pos = memchr::memchr(b'\n', &self.pgn[pos..]).map_or_else(|| self.pgn.len(), |p| pos + p + 1);
+                    pos = self.escape_line(pos);
+                },
+                b';' if after_tag_pair => {
+                    pos = self.line_comment(pos);
+                },
+                _ if after_tag_pair => {
+                    // Skip junk after a tag pair to the end of the line.
+                    let end = memchr::memchr(b'\n', &self.pgn[pos..]).map_or_else(|| self.pgn.len(), |p| pos + p);
+                    self.syntax_error(SyntaxError::UnknownToken, pos, trim_cr(self.pgn, pos, end));
+                    pos = end;
                 },
                 _ => break
             }
@@ -436,12 +975,140 @@ impl<'a, 'pgn, V: Visitor<'pgn>> Reader<'a, 'pgn, V> {
         pos
     }
 
+    /// Reads the rest of the line starting at `%` or `;` and reports its
+    /// span. Returns the position of the line break and the text after the
+    /// first byte.
+    fn rest_of_line(&mut self, start: usize) -> (usize, &'pgn [u8]) {
+        let pgn = self.pgn;
+        let end = memchr::memchr(b'\n', &pgn[start..]).map_or_else(|| pgn.len(), |p| start + p);
+        let content_end = trim_cr(pgn, start + 1, end);
+        self.span(start, content_end);
+        (end, &pgn[start + 1..content_end])
+    }
+
+    fn escape_line(&mut self, start: usize) -> usize {
+        let (end, line) = self.rest_of_line(start);
+        self.visitor.escape(line);
+        end
+    }
+
+    fn line_comment(&mut self, start: usize) -> usize {
+        let (end, comment) = self.rest_of_line(start);
+        self.visitor.line_comment(comment);
+        end
+    }
+
+    /// Reports escape lines in the whitespace between `start` and `end`.
+    fn scan_escape_lines(&mut self, mut pos: usize, end: usize) {
+        while let Some(p) = memchr::memchr(b'\n', &self.pgn[pos..end]) {
+            pos += p + 1;
+            if pos < end && self.pgn[pos] == b'%' {
+                pos = self.escape_line(pos);
+            }
+        }
+    }
+
+    fn scan_tag_pair(&mut self, start: usize) -> usize {
+        let mut pos = self.skip_blanks(start + 1);
+
+        let key_pos = pos;
+        while pos < self.pgn.len() && !matches!(self.pgn[pos], b' ' | b'\t' | b'\r' | b'\n' | b'"' | b']') {
+            pos += 1;
+        }
+        let key_end_pos = pos;
+
+        pos = self.skip_blanks(pos);
+        if self.pgn.get(pos) != Some(&b'"') {
+            // Malformed tag pair. Skip the rest of the line.
+            let end = memchr::memchr(b'\n', &self.pgn[pos..]).map_or_else(|| self.pgn.len(), |p| pos + p);
+            self.syntax_error(SyntaxError::InvalidHeader, start, trim_cr(self.pgn, start, end));
+            return end;
+        }
+        pos += 1;
+
+        let value_pos = pos;
+        let value_end_pos = loop {
+            match self.pgn.get(pos) {
+                Some(&b'\\') if matches!(self.pgn.get(pos + 1), Some(&b'"') | Some(&b'\\')) => pos += 2,
+                Some(&b'"') => {
+                    let end = self.skip_blanks(pos + 1);
+                    if self.pgn.get(end) == Some(&b']') {
+                        let value_end_pos = pos;
+                        pos = end + 1;
+                        break value_end_pos;
+                    }
+                    // Be lenient with unescaped quotes in values.
+                    pos += 1;
+                },
+                Some(&b'\n') | None => {
+                    let end = trim_cr(self.pgn, start, pos);
+
+                    // The last quote of the line may have been taken as
+                    // escaped, like in [Site "C:\"].
+                    let quote = (value_pos..end).rev()
+                        .find(|&p| self.pgn[p] == b'"' && self.pgn.get(self.skip_blanks(p + 1)) == Some(&b']'));
+                    if let Some(value_end_pos) = quote {
+                        pos = self.skip_blanks(value_end_pos + 1) + 1;
+                        break value_end_pos;
+                    }
+
+                    // Unterminated value or missing bracket.
+                    let closing = self.pgn[..end].iter().rposition(|c| !matches!(*c, b' ' | b'\t'))
+                        .filter(|&p| p >= value_pos && self.pgn[p] == b'"');
+                    match closing {
+                        Some(value_end_pos) if self.recover => {
+                            self.syntax_error(SyntaxError::InvalidHeader, start, end);
+                            self.span(start, end);
+                            self.visitor.header(&self.pgn[key_pos..key_end_pos], RawHeader(&self.pgn[value_pos..value_end_pos]));
+                        },
+                        _ => self.syntax_error(SyntaxError::InvalidHeader, start, end),
+                    }
+                    return pos;
+                },
+                Some(_) => pos += 1,
+            }
+        };
+
+        self.span(start, pos);
+        self.visitor.header(&self.pgn[key_pos..key_end_pos], RawHeader(&self.pgn[value_pos..value_end_pos]));
+        pos
+    }
+
+    fn skip_blanks(&self, mut pos: usize) -> usize {
+        while pos < self.pgn.len() && matches!(self.pgn[pos], b' ' | b'\t') {
+            pos += 1;
+        }
+        pos
+    }
+
+    /// Finds the end of a comment with contents starting at `pos`. Returns
+    /// the position of the closing `}`, or of the end of an unterminated
+    /// comment and `false`.
+    fn comment_end(&self, pos: usize) -> (usize, bool) {
+        let end = memchr::memchr(b'}', &self.pgn[pos..]).map(|p| pos + p);
+        let bound = end.unwrap_or(self.pgn.len());
+        if self.recover {
+            if let Some(boundary) = find_boundary(&self.pgn[pos..bound]) {
+                return (pos + boundary, false);
+            }
+        }
+        (bound, end.is_some())
+    }
+
+    fn skip_comment(&self, pos: usize) -> usize {
+        match self.comment_end(pos) {
+            (end, true) => end + 1,
+            (end, false) => end,
+        }
+    }
+
     fn skip_movetext(&mut self, mut pos: usize) -> usize {
+        self.skipped = true;
+
         while pos < self.pgn.len() {
             match self.pgn[pos] {
                 b'{' => {
-                    pos += 1;
-                    pos = memchr::memchr(b'}', &self.pgn[pos..]).map_or_else(|| self.pgn.len(), |p| pos + p + 1);
+                    pos = self.skip_comment(pos + 1);
                 },
                 b';' => {
                     pos += 1;
@@ -491,13 +1158,13 @@ impl<'a, 'pgn, V: Visitor<'pgn>> Reader<'a, 'pgn, V> {
                 b')' => {
                     if let Some(d) = depth.checked_sub(1) {
                         depth = d;
+                        pos += 1;
                     } else {
                         break;
                     }
                 },
                 b'{' => {
-                    pos += 1;
-                    pos = memchr::memchr(b'}', &self.pgn[pos..]).map_or_else(|| self.pgn.len(), |p| pos + p + 1);
+                    pos = self.skip_comment(pos + 1);
                 },
                 b';' => {
                     pos += 1;
@@ -547,19 +1214,143 @@ impl<'a, 'pgn, V: Visitor<'pgn>> Reader<'a, 'pgn, V> {
         pos
     }
 
+    fn syntax_error(&mut self, error: SyntaxError, start: usize, end: usize) {
+        self.span(start, end);
+        self.visitor.syntax_error(error, &self.pgn[start..end]);
+    }
+
+    fn parse_san(&self, token: &[u8]) -> Option<SanPlus> {
+        if is_null_move(token) {
+            return Some(SanPlus { san: San::Null, check: false, checkmate: false });
+        }
+
+        match self.notation {
+            Some(notation) => notation.parse(token),
+            None => SanPlus::from_bytes(token).ok().or_else(|| {
+                // Slow path for figurines.
+                if token.is_ascii() { None } else { Notation::english().parse(token) }
+            }),
+        }
+    }
+
+    /// Parses long algebraic notation like `e2-e4`, `e7xe8=Q` or `Ng1-f3`,
+    /// and coordinate notation like `e7e8q`. Moves of pieces without a
+    /// separator, like `Ng1f3`, are left to `parse_san`.
+    fn parse_long(&self, token: &[u8]) -> Option<MoveToken> {
+        let (token, check, checkmate) = match token.last() {
+            Some(&b'+') => (&token[..token.len() - 1], true, false),
+            Some(&b'#') => (&token[..token.len() - 1], false, true),
+            _ => (token, false, false),
+        };
+
+        let (role, token) = match token.split_first() {
+            Some((&b, rest)) if b.is_ascii_uppercase() => (Some(self.role(b)?), rest),
+            _ => (None, token),
+        };
+
+        let from = Square::from_bytes(token.get(0..2)?).ok()?;
+        let (separator, capture, token) = match token[2..].split_first() {
+            Some((&b'-', rest)) => (true, false, rest),
+            Some((&b'x', rest)) | Some((&b':', rest)) => (true, true, rest),
+            _ => (false, false, &token[2..]),
+        };
+
+        let to = Square::from_bytes(token.get(0..2)?).ok()?;
+        let promotion = match token[2..] {
+            [] => None,
+            [b'=', b] | [b] => Some(self.role(b).filter(|role| *role != Role::Pawn)?),
+            _ => return None,
+        };
+
+        let san = match role {
+            None if !separator => return Some(MoveToken::Uci(Uci::Normal { from, to, promotion })),
+            None => San::Normal { role: Role::Pawn, file: Some(from.file()), rank: Some(from.rank()), capture, to, promotion },
+            Some(_) if !separator || promotion.is_some() => return None,
+            Some(Role::King) if from.file() == 4 && from.rank() == to.rank() && (from.rank() == 0 || from.rank() == 7) && to.file() == 6 =>
+                San::Castle(CastlingSide::KingSide),
+            Some(Role::King) if from.file() == 4 && from.rank() == to.rank() && (from.rank() == 0 || from.rank() == 7) && to.file() == 2 =>
+                San::Castle(CastlingSide::QueenSide),
+            Some(role) => San::Normal { role, file: Some(from.file()), rank: Some(from.rank()), capture, to, promotion: None },
+        };
+
+        Some(MoveToken::San(SanPlus { san, check, checkmate }))
+    }
+
+    /// Parses a piece letter of the notation, like `N` or `q`.
+    fn role(&self, b: u8) -> Option<Role> {
+        let b = match self.notation {
+            Some(notation) => notation.translate(b.to_ascii_uppercase()),
+            None => b,
+        };
+        Role::from_char(char::from(b).to_ascii_lowercase())
+    }
+
+    fn with_suffix(&self, san: San, pos: usize) -> (SanPlus, usize) {
+        match self.pgn.get(pos) {
+            Some(&b'+') => (SanPlus { san, check: true, checkmate: false }, pos + 1),
+            Some(&b'#') => (SanPlus { san, check: false, checkmate: true }, pos + 1),
+            _ => (SanPlus { san, check: false, checkmate: false }, pos),
+        }
+    }
+
+    fn read_number(&mut self, start: usize) -> usize {
+        let end = self.skip_token(start);
+        let number = &self.pgn[start..end];
+        if !number.iter().all(|c| c.is_ascii_digit()) {
+            self.syntax_error(SyntaxError::UnknownToken, start, end);
+            return end;
+        }
+
+        // Move number indication, followed by zero or more dots.
+        let dots = self.pgn[end..].iter().take_while(|c| **c == b'.').count();
+        if let Ok(number) = btou(number) {
+            let color = if dots < 2 { Color::White } else { Color::Black };
+            self.span(start, end + dots);
+            self.visitor.move_number(number, color);
+        } else {
+            self.syntax_error(SyntaxError::UnknownToken, start, end + dots);
+        }
+
+        end + dots
+    }
+
+    /// Closes the open variations and skips the rest of the movetext, as
+    /// requested by the visitor.
+    fn skip_rest(&mut self, pos: usize, depth: usize) -> usize {
+        for _ in 0..depth {
+            self.span(pos, pos);
+            self.visitor.end_variation();
+        }
+        self.skip_movetext(pos)
+    }
+
     fn scan_movetext(&mut self, mut pos: usize) -> usize {
+        let mut depth = 0usize;
+
         while pos < self.pgn.len() {
             match self.pgn[pos] {
                 b'{' => {
+                    let start = pos;
                     pos += 1;
-                    pos = if let Some(delta) = memchr::memchr(b'}', &self.pgn[pos..]) {
-                        let end = pos + delta;
-                        self.visitor.comment(&self.pgn[pos..end]);
-                        end + 1
-                    } else {
-                        self.visitor.comment(&self.pgn[pos..]);
-                        self.pgn.len()
+                    pos = match self.comment_end(pos) {
+                        (end, true) => {
+                            self.span(start, end + 1);
+                            self.visitor.comment(&self.pgn[pos..end]);
+                            end + 1
+                        },
+                        (end, false) if self.recover => {
+                            self.syntax_error(SyntaxError::UnterminatedComment, start, end);
+                            end
+                        },
+                        (end, false) => {
+                            self.span(start, end);
+                            self.visitor.comment(&self.pgn[pos..end]);
+                            end
+                        },
                     };
+                    if let Skip(true) = self.visitor.skip_movetext() {
+                        return self.skip_rest(pos, depth);
+                    }
                 },
                 b'\n' => {
                     pos += 1;
@@ -568,8 +1359,7 @@ impl<'a, 'pgn, V: Visitor<'pgn>> Reader<'a, 'pgn, V> {
                     }
                     match self.pgn[pos] {
                         b'%' => {
-                            pos += 1;
-                            pos = memchr::memchr(b'\n', &self.pgn[pos..]).map_or_else(|| self.pgn.len(), |p| pos + p);
+                            pos = self.escape_line(pos);
                         },
                         b'[' | b'\n' => {
                             break
@@ -584,62 +1374,122 @@ impl<'a, 'pgn, V: Visitor<'pgn>> Reader<'a, 'pgn, V> {
                     }
                 },
                 b';' => {
-                    pos += 1;
-                    pos = memchr::memchr(b'\n', &self.pgn[pos..]).map_or_else(|| self.pgn.len(), |p| pos + p);
+                    pos = self.line_comment(pos);
                 },
                 b'1' => {
+                    let start = pos;
                     pos += 1;
                     if self.pgn[pos..].starts_with(b"-0") {
                         pos += 2;
-                        self.visitor.outcome(Outcome::Decisive { winner: Color::White });
+                        self.span(start, pos);
+                        self.visitor.outcome(Some(Outcome::Decisive { winner: Color::White }));
                     } else if self.pgn[pos..].starts_with(b"/2-1/2") {
                         pos += 6;
-                        self.visitor.outcome(Outcome::Draw);
+                        self.span(start, pos);
+                        self.visitor.outcome(Some(Outcome::Draw));
                     } else {
-                        pos = self.skip_token(pos);
+                        pos = self.read_number(start);
                     }
                 },
                 b'0' => {
+                    let start = pos;
                     pos += 1;
                     if self.pgn[pos..].starts_with(b"-1") {
                         pos += 2;
-                        self.visitor.outcome(Outcome::Decisive { winner: Color::Black });
+                        self.span(start, pos);
+                        self.visitor.outcome(Some(Outcome::Decisive { winner: Color::Black }));
                     } else if self.pgn[pos..].starts_with(b"-0-0") {
-                        pos += 4;
-                        self.visitor.san(San::Castle(CastlingSide::QueenSide));
+                        let (san, end) = self.with_suffix(San::Castle(CastlingSide::QueenSide), pos + 4);
+                        pos = end;
+                        self.span(start, pos);
+                        self.visitor.san(san);
+                        if let Skip(true) = self.visitor.skip_movetext() {
+                            return self.skip_rest(pos, depth);
+                        }
                     } else if self.pgn[pos..].starts_with(b"-0") {
-                        pos += 2;
-                        self.visitor.san(San::Castle(CastlingSide::KingSide));
+                        let (san, end) = self.with_suffix(San::Castle(CastlingSide::KingSide), pos + 2);
+                        pos = end;
+                        self.span(start, pos);
+                        self.visitor.san(san);
+                        if let Skip(true) = self.visitor.skip_movetext() {
+                            return self.skip_rest(pos, depth);
+                        }
+                    } else if self.pgn[pos..].starts_with(b"000") && self.skip_token(pos) == start + 4 {
+                        pos = start + 4;
+                        self.span(start, pos);
+                        self.visitor.san(SanPlus { san: San::Null, check: false, checkmate: false });
+                        if let Skip(true) = self.visitor.skip_movetext() {
+                            return self.skip_rest(pos, depth);
+                        }
                     } else {
-                        pos = self.skip_token(pos);
+                        pos = self.read_number(start);
                     }
                 },
                 b'(' => {
+                    self.span(pos, pos + 1);
                     pos += 1;
+                    depth += 1;
                     if let Skip(true) = self.visitor.begin_variation() {
                         pos = self.skip_variation(pos);
                     }
                 },
                 b')' => {
+                    if let Some(d) = depth.checked_sub(1) {
+                        depth = d;
+                        self.span(pos, pos + 1);
+                        self.visitor.end_variation();
+                    } else {
+                        self.syntax_error(SyntaxError::UnmatchedParenthesis, pos, pos + 1);
+                    }
+                    pos += 1;
+                },
+                b'}' => {
+                    self.syntax_error(SyntaxError::UnmatchedBrace, pos, pos + 1);
                     pos += 1;
-                    self.visitor.end_variation();
                 },
                 b'!' | b'?' | b'$' => {
                     let start = pos;
                     pos = self.skip_token(pos + 1);
                     if let Ok(nag) = Nag::from_bytes(&self.pgn[start..pos]) {
+                        self.span(start, pos);
                         self.visitor.nag(nag);
+                        if let Skip(true) = self.visitor.skip_movetext() {
+                            return self.skip_rest(pos, depth);
+                        }
+                    } else {
+                        self.syntax_error(SyntaxError::InvalidNag, start, pos);
                     }
                 },
-                b' ' | b'\t' | b'\r' | b'P' | b'.' => {
+                b' ' | b'\t' | b'\r' | b'.' => {
                     pos += 1;
                 },
+                b'P' if self.notation.is_none_or(|n| !n.is_piece_letter(b'P')) => {
+                    pos += 1;
+                },
+                b'*' => {
+                    self.span(pos, pos + 1);
+                    self.visitor.outcome(None);
+                    pos += 1;
+                },
+                b'2'..=b'9' => {
+                    pos = self.read_number(pos);
+                },
                 _ => {
                     let end = self.skip_token(pos + 1);
-                    if self.pgn[pos] > b'9' {
-                        if let Ok(san) = San::from_bytes(&self.pgn[pos..end]) {
-                            self.visitor.san(san);
+                    let token = &self.pgn[pos..end];
+                    if token[0] <= b'9' && !is_null_move(token) {
+                        self.syntax_error(SyntaxError::UnknownToken, pos, end);
+                    } else if let Some(m) = self.parse_long(token).or_else(|| self.parse_san(token).map(MoveToken::San)) {
+                        self.span(pos, end);
+                        match m {
+                            MoveToken::San(san) => self.visitor.san(san),
+                            MoveToken::Uci(uci) => self.visitor.uci(uci),
+                        }
+                        if let Skip(true) = self.visitor.skip_movetext() {
+                            return self.skip_rest(end, depth);
                         }
+                    } else {
+                        self.syntax_error(SyntaxError::InvalidSan, pos, end);
                     }
                     pos = end;
                 },
@@ -683,6 +1533,510 @@ impl<'a, 'pgn, V: Visitor<'pgn>> Iterator for Iter<'a, 'pgn, V> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::io::BufReader;
+
+    struct _AssertObjectSafe<'pgn, R>(Box<dyn Visitor<'pgn, Result=R>>);
+
+    struct Spans(Vec<Span>);
+
+    impl<'pgn> Visitor<'pgn> for Spans {
+        type Result = ();
+
+        fn span(&mut self, span: Span) {
+            self.0.push(span);
+        }
+
+        fn end_game(&mut self, _game: &'pgn [u8]) { }
+    }
+
+    #[test]
+    fn test_spans() {
+        let pgn = b"\xef\xbb\xbf[Event \"?\"]\n\n1. e4 $1 { x } (1. d4 0-0) 1-0\n\n2... Nf6 *";
+
+        let mut visitor = Spans(Vec::new());
+        Reader::new(&mut visitor, &pgn[..]).read_all();
+
+        let tokens: Vec<&[u8]> = visitor.0.iter().map(|s| &pgn[s.start..s.end]).collect();
+        assert_eq!(tokens, vec![
+            &b"[Event \"?\"]"[..], b"1.", b"e4", b"$1", b"{ x }", b"(", b"1.", b"d4", b"0-0", b")", b"1-0",
+            &pgn[3..48], b"2...", b"Nf6", b"*", b"2... Nf6 *", b"",
+        ][..]);
+
+        assert_eq!(visitor.0[11].location, Location { line: 1, column: 4 });
+        assert_eq!(visitor.0[13].location, Location { line: 5, column: 6 });
+        assert_eq!(visitor.0[14].location, Location { line: 5, column: 10 });
+        assert_eq!(visitor.0[15].location, Location { line: 5, column: 1 });
+
+        let mut buffered = Spans(Vec::new());
+        BufferedReader::new(&mut buffered, BufReader::with_capacity(5, &pgn[..])).read_all().expect("read");
+        assert_eq!(buffered.0, visitor.0);
+    }
+
+    struct SyntaxErrors<'pgn>(Vec<(SyntaxError, &'pgn [u8])>, usize);
+
+    impl<'pgn> Visitor<'pgn> for SyntaxErrors<'pgn> {
+        type Result = ();
+
+        fn end_variation(&mut self) {
+            self.1 += 1;
+        }
+
+        fn syntax_error(&mut self, error: SyntaxError, token: &'pgn [u8]) {
+            self.0.push((error, token));
+        }
+
+        fn end_game(&mut self, _game: &'pgn [u8]) { }
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        let mut visitor = SyntaxErrors(Vec::new(), 0);
+        Reader::new(&mut visitor, b"1. e4 e5 2... Nf3 (2. d4 $1 !?) 12. O-O-O 1/2-1/2 *").read_all();
+        assert_eq!(visitor.0, vec![]);
+        assert_eq!(visitor.1, 1);
+
+        let mut visitor = SyntaxErrors(Vec::new(), 0);
+        Reader::new(&mut visitor, b"1. Nf9 $256 } e4) + 1/2 (Nf3 ) 2x").read_all();
+        assert_eq!(visitor.0, vec![
+            (SyntaxError::InvalidSan, &b"Nf9"[..]),
+            (SyntaxError::InvalidNag, b"$256"),
+            (SyntaxError::UnmatchedBrace, b"}"),
+            (SyntaxError::UnmatchedParenthesis, b")"),
+            (SyntaxError::UnknownToken, b"+"),
+            (SyntaxError::UnknownToken, b"1/2"),
+            (SyntaxError::UnknownToken, b"2x"),
+        ]);
+        assert_eq!(visitor.1, 1);
+    }
+
+    #[derive(Default)]
+    struct SkipVariations(Vec<String>);
+
+    impl<'pgn> Visitor<'pgn> for SkipVariations {
+        type Result = ();
+
+        fn san(&mut self, san_plus: SanPlus) {
+            self.0.push(san_plus.to_string());
+        }
+
+        fn begin_variation(&mut self) -> Skip {
+            self.0.push("(".to_owned());
+            Skip(true)
+        }
+
+        fn end_variation(&mut self) {
+            self.0.push(")".to_owned());
+        }
+
+        fn syntax_error(&mut self, error: SyntaxError, _token: &'pgn [u8]) {
+            self.0.push(error.to_string());
+        }
+
+        fn end_game(&mut self, _game: &'pgn [u8]) { }
+    }
+
+    #[test]
+    fn test_skip_nested_variation() {
+        let mut visitor = SkipVariations::default();
+        Reader::new(&mut visitor, b"1. e4 (1. d4 (1. c4) 1... d5) 1... e5 (1... c5 (1... e6)) *").read_all();
+        assert_eq!(visitor.0, vec!["e4", "(", ")", "e5", "(", ")"]);
+    }
+
+    struct StopAfter(usize);
+
+    impl<'pgn> Visitor<'pgn> for StopAfter {
+        type Result = ();
+
+        fn end_game(&mut self, _game: &'pgn [u8]) {
+            self.0 -= 1;
+        }
+
+        fn stop(&mut self) -> Stop {
+            Stop(self.0 == 0)
+        }
+    }
+
+    #[test]
+    fn test_stop() {
+        let pgn = b"1. e4 *\n\n1. d4 *\n\n1. c4 *\n";
+
+        let mut visitor = StopAfter(2);
+        let mut reader = Reader::new(&mut visitor, &pgn[..]);
+        reader.read_all();
+        assert!(reader.stopped());
+        assert_eq!(reader.position(), 18);
+        assert_eq!(reader.read_game(), None);
+
+        let mut visitor = StopAfter(2);
+        let mut reader = BufferedReader::new(&mut visitor, BufReader::with_capacity(3, &pgn[..]));
+        reader.read_all().expect("read");
+        assert!(reader.stopped());
+        assert_eq!(reader.position(), 18);
+
+        let mut visitor = StopAfter(5);
+        let mut reader = Reader::new(&mut visitor, &pgn[..]);
+        reader.read_all();
+        assert!(!reader.stopped());
+        assert_eq!(reader.position(), pgn.len());
+    }
+
+    struct FirstMoves(Vec<SanPlus>, usize);
 
-    struct _AssertObjectSafe<'pgn, R>(Box<Visitor<'pgn, Result=R>>);
+    impl<'pgn> Visitor<'pgn> for FirstMoves {
+        type Result = (Vec<SanPlus>, &'pgn [u8]);
+
+        fn san(&mut self, san_plus: SanPlus) {
+            self.0.push(san_plus);
+        }
+
+        fn skip_movetext(&mut self) -> Skip {
+            Skip(self.0.len() >= self.1)
+        }
+
+        fn end_game(&mut self, game: &'pgn [u8]) -> Self::Result {
+            (::std::mem::take(&mut self.0), game)
+        }
+    }
+
+    #[test]
+    fn test_skip_movetext() {
+        let pgn = b"1. e4 e5 (1... c5 { no\n\n[Event } 2. Nf3) 2. O-O 1-0\n\n[Event \"?\"]\n1. d4 *";
+
+        let mut visitor = FirstMoves(Vec::new(), 2);
+        let games: Vec<_> = Reader::new(&mut visitor, &pgn[..]).into_iter().collect();
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].0.len(), 2);
+        assert_eq!(games[0].1, &pgn[..53]);
+        assert_eq!(games[1].0.len(), 1);
+
+        let mut visitor = FirstMoves(Vec::new(), 4);
+        let games: Vec<_> = Reader::new(&mut visitor, &pgn[..]).into_iter().collect();
+        assert_eq!(games[0].0.len(), 4);
+        assert_eq!(games[0].1, &pgn[..53]);
+        // Skipping from inside variations closes them.
+        let mut visitor = StopAtComment(Vec::new());
+        Reader::new(&mut visitor, b"1. e4 (1. d4 (1. c4 { stop } 1... e5) 1... d5) 1... e5 *").read_all();
+        assert_eq!(visitor.0, vec!["e4", "(", "d4", "(", "c4", "{}", ")", ")"]);
+    }
+
+    struct StopAtComment(Vec<String>);
+
+    impl<'pgn> Visitor<'pgn> for StopAtComment {
+        type Result = ();
+
+        fn san(&mut self, san_plus: SanPlus) {
+            self.0.push(san_plus.to_string());
+        }
+
+        fn comment(&mut self, _comment: &'pgn [u8]) {
+            self.0.push("{}".to_owned());
+        }
+
+        fn begin_variation(&mut self) -> Skip {
+            self.0.push("(".to_owned());
+            Skip(false)
+        }
+
+        fn end_variation(&mut self) {
+            self.0.push(")".to_owned());
+        }
+
+        fn skip_movetext(&mut self) -> Skip {
+            Skip(self.0.last().is_some_and(|e| e == "{}"))
+        }
+
+        fn end_game(&mut self, _game: &'pgn [u8]) { }
+    }
+
+    struct MoveNumbers(Vec<(u32, Color)>);
+
+    impl<'pgn> Visitor<'pgn> for MoveNumbers {
+        type Result = ();
+
+        fn move_number(&mut self, number: u32, color: Color) {
+            self.0.push((number, color));
+        }
+
+        fn end_game(&mut self, _game: &'pgn [u8]) { }
+    }
+
+    #[test]
+    fn test_move_numbers() {
+        let mut visitor = MoveNumbers(Vec::new());
+        Reader::new(&mut visitor, b"1.e4 e5 2. Nf3 { 3. } 2... Nc6 (2...d6 3 d4) 30.. Bb5 1-0").read_all();
+        assert_eq!(visitor.0, vec![
+            (1, Color::White),
+            (2, Color::White),
+            (2, Color::Black),
+            (2, Color::Black),
+            (3, Color::White),
+            (30, Color::Black),
+        ]);
+    }
+
+    struct Sans(Vec<SanPlus>);
+
+    impl<'pgn> Visitor<'pgn> for Sans {
+        type Result = ();
+
+        fn san(&mut self, san_plus: SanPlus) {
+            self.0.push(san_plus);
+        }
+
+        fn end_game(&mut self, _game: &'pgn [u8]) { }
+    }
+
+    #[test]
+    fn test_null_moves() {
+        let mut visitor = Sans(Vec::new());
+        Reader::new(&mut visitor, b"1. e4 -- 2. Z0 0000 3. @@@@ 000 0-0 *").read_all();
+        assert_eq!(visitor.0.len(), 6);
+        assert!(visitor.0[1..5].iter().all(|san_plus| san_plus.san == San::Null));
+        assert_eq!(visitor.0[5].san, San::Castle(CastlingSide::KingSide));
+    }
+
+    #[test]
+    fn test_suffixes() {
+        let mut visitor = Sans(Vec::new());
+        Reader::new(&mut visitor, b"1. f3 e5 2. g4+ Qh4#! 3. O-O+ 0-0-0# 4. 0-0+?? *").read_all();
+        let sans: Vec<_> = visitor.0.iter().map(|san_plus| san_plus.to_string()).collect();
+        assert_eq!(sans, vec!["f3", "e5", "g4+", "Qh4#", "O-O+", "O-O-O#", "O-O+"]);
+    }
+
+    struct Moves(Vec<String>);
+
+    impl<'pgn> Visitor<'pgn> for Moves {
+        type Result = ();
+
+        fn san(&mut self, san_plus: SanPlus) {
+            self.0.push(san_plus.to_string());
+        }
+
+        fn uci(&mut self, uci: Uci) {
+            self.0.push(format!("uci:{}", uci));
+        }
+
+        fn end_game(&mut self, _game: &'pgn [u8]) { }
+    }
+
+    #[test]
+    fn test_long_algebraic() {
+        let mut visitor = Moves(Vec::new());
+        Reader::new(&mut visitor, b"1. e2-e4 e7e5 2. Ng1-f3+ Nb8xc6 3. Bf1:b5 Ng8f6 4. e1g1 a7a6 5. e7xe8=Q# a2a1n \
+                                    6. Ke1-g1 Ke8-c8 Xg1-f3 Ng1-f3=Q *").read_all();
+        assert_eq!(visitor.0, vec![
+            "e2e4", "uci:e7e5", "Ng1f3+", "Nb8xc6", "Bf1xb5", "Ng8f6",
+            "uci:e1g1", "uci:a7a6", "e7xe8=Q#", "uci:a2a1n", "O-O", "O-O-O",
+        ]);
+
+        let mut visitor = Moves(Vec::new());
+        Reader::new(&mut visitor, b"1. e2-e4 e7-e5 2. e4-e5 3. Sg1-f3 e2-e1=D a2a1p *").notation(Notation::german()).read_all();
+        assert_eq!(visitor.0, vec!["e2e4", "e7e5", "e4e5", "Ng1f3", "e2e1=Q"]);
+    }
+
+    struct Outcomes<'pgn>(Option<RawHeader<'pgn>>, Vec<Option<Outcome>>);
+
+    impl<'pgn> Visitor<'pgn> for Outcomes<'pgn> {
+        type Result = Result<(), ResultMismatch<'pgn>>;
+
+        fn header(&mut self, key: &'pgn [u8], value: RawHeader<'pgn>) {
+            if key == b"Result" {
+                self.0 = Some(value);
+            }
+        }
+
+        fn outcome(&mut self, outcome: Option<Outcome>) {
+            self.1.push(outcome);
+        }
+
+        fn end_game(&mut self, _game: &'pgn [u8]) -> Self::Result {
+            let header = self.0.take().unwrap_or(RawHeader(b"*"));
+            header.check_outcome(self.1.last().cloned().unwrap_or(None))
+        }
+    }
+
+    #[test]
+    fn test_outcomes() {
+        let pgn = b"[Result \"*\"]\n\n1. e4 *\n\n\
+                    [Result \"1-0\"]\n\n1. e4 *\n\n\
+                    [Result \"1/2-1/2\"]\n\n1. e4 1/2-1/2\n\n\
+                    [Result \"?\"]\n\n1. e4 0-1";
+
+        let mut visitor = Outcomes(None, Vec::new());
+        let results: Vec<_> = Reader::new(&mut visitor, &pgn[..]).into_iter().collect();
+        assert_eq!(visitor.1, vec![None, None, Some(Outcome::Draw), Some(Outcome::Decisive { winner: Color::Black })]);
+        assert_eq!(results, vec![
+            Ok(()),
+            Err(ResultMismatch { header: RawHeader(b"1-0"), marker: None }),
+            Ok(()),
+            Err(ResultMismatch { header: RawHeader(b"?"), marker: Some(Outcome::Decisive { winner: Color::Black }) }),
+        ]);
+        assert_eq!(results[1].as_ref().unwrap_err().to_string(), "result header \"1-0\" does not match game termination marker *");
+    }
+
+    struct Lines<'pgn> {
+        lines: Vec<(char, &'pgn [u8])>,
+        spans: Vec<Span>,
+        skip: bool,
+    }
+
+    impl<'pgn> Visitor<'pgn> for Lines<'pgn> {
+        type Result = ();
+
+        fn span(&mut self, span: Span) {
+            self.spans.push(span);
+        }
+
+        fn end_headers(&mut self) -> Skip {
+            Skip(self.skip)
+        }
+
+        fn line_comment(&mut self, comment: &'pgn [u8]) {
+            self.lines.push((';', comment));
+        }
+
+        fn escape(&mut self, line: &'pgn [u8]) {
+            self.lines.push(('%', line));
+        }
+
+        fn end_game(&mut self, _game: &'pgn [u8]) { }
+    }
+
+    #[test]
+    fn test_line_comments() {
+        let pgn = b"%header\n[Event \"?\"]\n%tags\r\n\n1. e4 ; best by test\r\n%engine 1\n1... e5 ;\n*\n\n%trailer\n\n1. d4 *";
+
+        let mut visitor = Lines { lines: Vec::new(), spans: Vec::new(), skip: false };
+        Reader::new(&mut visitor, &pgn[..]).read_all();
+        assert_eq!(visitor.lines, vec![
+            ('%', &b"header"[..]),
+            ('%', b"tags"),
+            (';', b" best by test"),
+            ('%', b"engine 1"),
+            (';', b""),
+            ('%', b"trailer"),
+        ]);
+        let tokens: Vec<&[u8]> = visitor.spans.iter().map(|s| &pgn[s.start..s.end]).collect();
+        assert!(tokens.contains(&&b"; best by test"[..]));
+        assert!(tokens.contains(&&b"%trailer"[..]));
+
+        let mut visitor = Lines { lines: Vec::new(), spans: Vec::new(), skip: true };
+        Reader::new(&mut visitor, &pgn[..]).read_all();
+        assert_eq!(visitor.lines, vec![('%', &b"header"[..]), ('%', b"tags")]);
+    }
+
+    #[derive(Default)]
+    struct Recovery {
+        headers: Vec<String>,
+        errors: Vec<(SyntaxError, String)>,
+        sans: usize,
+    }
+
+    impl<'pgn> Visitor<'pgn> for Recovery {
+        type Result = usize;
+
+        fn header(&mut self, _key: &'pgn [u8], value: RawHeader<'pgn>) {
+            self.headers.push(value.decode_utf8_lossy().into_owned());
+        }
+
+        fn san(&mut self, _san_plus: SanPlus) {
+            self.sans += 1;
+        }
+
+        fn syntax_error(&mut self, error: SyntaxError, token: &'pgn [u8]) {
+            self.errors.push((error, String::from_utf8_lossy(token).into_owned()));
+        }
+
+        fn end_game(&mut self, _game: &'pgn [u8]) -> usize {
+            ::std::mem::take(&mut self.sans)
+        }
+    }
+
+    #[test]
+    fn test_recover() {
+        let pgn = b"[Event \"A\"]\n\n1. e4 { runaway\n\n\
+                    [Event \"B\"]\r\n[Site \"b\" \r\n\n1. d4 d5 *\n\n\
+                    [Event \"C\"]\n[Round]\n\n1. c4 { closed } c5 *";
+
+        let mut visitor = Recovery::default();
+        let games: Vec<_> = Reader::new(&mut visitor, &pgn[..]).into_iter().collect();
+        assert_eq!(games, vec![2]);
+        assert_eq!(visitor.errors, vec![]);
+
+        let mut visitor = Recovery::default();
+        let mut reader = Reader::new(&mut visitor, &pgn[..]);
+        reader.recover(true);
+        let games: Vec<_> = reader.into_iter().collect();
+        assert_eq!(games, vec![1, 2, 2]);
+        assert_eq!(visitor.headers, vec!["A", "B", "b", "C"]);
+        assert_eq!(visitor.errors, vec![
+            (SyntaxError::UnterminatedComment, "{ runaway".to_owned()),
+            (SyntaxError::InvalidHeader, "[Site \"b\" ".to_owned()),
+            (SyntaxError::InvalidHeader, "[Round]".to_owned()),
+        ]);
+
+        let mut visitor = Recovery::default();
+        let mut reader = BufferedReader::new(&mut visitor, BufReader::with_capacity(5, &pgn[..]));
+        reader.recover(true);
+        let games = reader.into_iter().collect::<Result<Vec<_>, _>>().expect("read");
+        assert_eq!(games, vec![1, 2, 2]);
+    }
+
+    struct Headers<'pgn>(Vec<(&'pgn [u8], RawHeader<'pgn>)>);
+
+    impl<'pgn> Visitor<'pgn> for Headers<'pgn> {
+        type Result = usize;
+
+        fn header(&mut self, key: &'pgn [u8], value: RawHeader<'pgn>) {
+            self.0.push((key, value));
+        }
+
+        fn end_game(&mut self, _game: &'pgn [u8]) -> usize {
+            self.0.len()
+        }
+    }
+
+    #[test]
+    fn test_tag_pairs() {
+        let pgn = b"[White \"a\"] [Black \"b\"]  \r\n\
+                    [ Event\t\"The \\\"Match\\\" \\\\o/\" ]\n\
+                    [Site \"Unescaped \"quotes\"\"]\n\
+                    [Broken]\n\
+                    [Round \"unterminated\n\
+                    [Date \"?\"]\n\
+                    \n\
+                    1. e4 *\n\n[Next \"\"]";
+
+        let mut visitor = Headers(Vec::new());
+        let games: Vec<_> = Reader::new(&mut visitor, &pgn[..]).into_iter().collect();
+        assert_eq!(games, vec![5, 6]);
+
+        let headers: Vec<_> = visitor.0.iter().map(|&(k, v)| (k, v.decode())).collect();
+        assert_eq!(headers, vec![
+            (&b"White"[..], Cow::Borrowed(&b"a"[..])),
+            (b"Black", Cow::Borrowed(b"b")),
+            (b"Event", Cow::Owned(b"The \"Match\" \\o/".to_vec())),
+            (b"Site", Cow::Borrowed(b"Unescaped \"quotes\"")),
+            (b"Date", Cow::Borrowed(b"?")),
+            (b"Next", Cow::Borrowed(b"")),
+        ]);
+    }
+
+    #[test]
+    fn test_tag_pair_lines() {
+        let pgn = b"[Event \"a\\\n[Site \"C:\\\"]\n[White \"w\"] ; note\n[Black \"b\"] junk\r\n\n1. e4 e5 *";
+
+        let mut visitor = Recovery::default();
+        let games: Vec<_> = Reader::new(&mut visitor, &pgn[..]).into_iter().collect();
+        assert_eq!(games, vec![2]);
+        assert_eq!(visitor.headers, vec!["C:\\", "w", "b"]);
+        assert_eq!(visitor.errors, vec![
+            (SyntaxError::InvalidHeader, "[Event \"a\\".to_owned()),
+            (SyntaxError::UnknownToken, "junk".to_owned()),
+        ]);
+
+        let mut visitor = Lines { lines: Vec::new(), spans: Vec::new(), skip: false };
+        Reader::new(&mut visitor, &pgn[..]).read_all();
+        assert_eq!(visitor.lines, vec![(';', &b" note"[..])]);
+    }
 }
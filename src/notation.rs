@@ -0,0 +1,207 @@
+// This file is part of the pgn-reader library.
+// Copyright (C) 2017 Niklas Fiekas <niklas.fiekas@backscattering.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use SanPlus;
+
+/// Piece letters in the order king, queen, rook, bishop, knight.
+const ENGLISH: [u8; 5] = *b"KQRBN";
+
+/// Longest move in standard notation, like `Qa1xb2=Q#`, with some room.
+const MAX_SAN_LEN: usize = 16;
+
+/// Piece letters used for moves in the movetext, like `Sf3` in German
+/// sources.
+///
+/// Figurines like `♘f3` are always understood, regardless of the notation.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{Reader, Notation, Visitor, SanPlus};
+///
+/// struct Moves(Vec<String>);
+///
+/// impl<'pgn> Visitor<'pgn> for Moves {
+///     type Result = ();
+///
+///     fn san(&mut self, san_plus: SanPlus) {
+///         self.0.push(san_plus.to_string());
+///     }
+///
+///     fn end_game(&mut self, _game: &'pgn [u8]) { }
+/// }
+///
+/// let mut moves = Moves(Vec::new());
+/// let mut reader = Reader::new(&mut moves, "1. e4 e5 2. Sf3 Sc6 3. ♗b5 a6 4. Lxc6 dxc6 5. 0-0 *".as_bytes());
+/// reader.notation(Notation::german());
+/// reader.read_all();
+///
+/// assert_eq!(moves.0, vec!["e4", "e5", "Nf3", "Nc6", "Bb5", "a6", "Bxc6", "dxc6", "O-O"]);
+/// ```
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct Notation {
+    letters: [u8; 5],
+}
+
+impl Default for Notation {
+    fn default() -> Notation {
+        Notation::english()
+    }
+}
+
+impl Notation {
+    /// Creates a notation with the given uppercase letters for king, queen,
+    /// rook, bishop and knight.
+    pub fn new(king: u8, queen: u8, rook: u8, bishop: u8, knight: u8) -> Notation {
+        Notation { letters: [king, queen, rook, bishop, knight] }
+    }
+
+    /// Standard notation: `KQRBN`.
+    pub fn english() -> Notation {
+        Notation { letters: ENGLISH }
+    }
+
+    /// German notation: `KDTLS`.
+    pub fn german() -> Notation {
+        Notation::new(b'K', b'D', b'T', b'L', b'S')
+    }
+
+    /// French notation: `RDTFC`.
+    pub fn french() -> Notation {
+        Notation::new(b'R', b'D', b'T', b'F', b'C')
+    }
+
+    /// Spanish notation: `RDTAC`.
+    pub fn spanish() -> Notation {
+        Notation::new(b'R', b'D', b'T', b'A', b'C')
+    }
+
+    /// Italian notation: `RDTAC`.
+    pub fn italian() -> Notation {
+        Notation::new(b'R', b'D', b'T', b'A', b'C')
+    }
+
+    /// Dutch notation: `KDTLP`.
+    pub fn dutch() -> Notation {
+        Notation::new(b'K', b'D', b'T', b'L', b'P')
+    }
+
+    /// Tests if this is the standard notation.
+    pub fn is_english(&self) -> bool {
+        self.letters == ENGLISH
+    }
+
+    /// Tests if `b` is a piece letter in this notation.
+    pub(crate) fn is_piece_letter(&self, b: u8) -> bool {
+        self.letters.contains(&b)
+    }
+
+    /// Translates a piece letter of this notation to the standard letter,
+    /// leaving other bytes as they are.
+    pub(crate) fn translate(&self, b: u8) -> u8 {
+        match self.letters.iter().position(|l| *l == b) {
+            Some(index) => ENGLISH[index],
+            None => b,
+        }
+    }
+
+    /// Parses a move in this notation, also accepting figurines.
+    pub(crate) fn parse(&self, token: &[u8]) -> Option<SanPlus> {
+        let mut buf = [0; MAX_SAN_LEN];
+        let mut len = 0;
+        let mut pos = 0;
+
+        while pos < token.len() {
+            let b = if let Some(index) = figurine(&token[pos..]) {
+                pos += 3;
+                match ENGLISH.get(index) {
+                    Some(&letter) => letter,
+                    None => continue, // Pawn
+                }
+            } else {
+                pos += 1;
+                self.translate(token[pos - 1])
+            };
+
+            *buf.get_mut(len)? = b;
+            len += 1;
+        }
+
+        SanPlus::from_bytes(&buf[..len]).ok()
+    }
+}
+
+/// Recognizes figurines `♔♕♖♗♘♙` and `♚♛♜♝♞♟`, returning the index into
+/// the piece letters (or 5 for pawns).
+fn figurine(s: &[u8]) -> Option<usize> {
+    match *s {
+        [0xe2, 0x99, b, ..] if (0x94..=0x99).contains(&b) => Some(usize::from(b - 0x94)),
+        [0xe2, 0x99, b, ..] if (0x9a..=0x9f).contains(&b) => Some(usize::from(b - 0x9a)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::BufReader;
+    use {Reader, BufferedReader, Visitor};
+
+    struct Moves(Vec<String>);
+
+    impl<'pgn> Visitor<'pgn> for Moves {
+        type Result = Vec<String>;
+
+        fn san(&mut self, san_plus: SanPlus) {
+            self.0.push(san_plus.to_string());
+        }
+
+        fn end_game(&mut self, _game: &'pgn [u8]) -> Vec<String> {
+            ::std::mem::take(&mut self.0)
+        }
+    }
+
+    fn read(pgn: &str, notation: Notation) -> Vec<String> {
+        let mut visitor = Moves(Vec::new());
+        let mut reader = Reader::new(&mut visitor, pgn.as_bytes());
+        reader.notation(notation);
+        reader.read_game().expect("game")
+    }
+
+    #[test]
+    fn test_localized() {
+        assert_eq!(read("1. Pf3 Pc6 2. Pe5 e6 3. Pxc6 bxc6", Notation::dutch()),
+                   vec!["Nf3", "Nc6", "Ne5", "e6", "Nxc6", "bxc6"]);
+        assert_eq!(read("1. e4 e5 2. Re2 Dh4 3. Cf3 Fc5 4. c4 Dxf2#", Notation::french()),
+                   vec!["e4", "e5", "Ke2", "Qh4", "Nf3", "Bc5", "c4", "Qxf2#"]);
+        assert_eq!(read("1. e4 e5 2. Re2 Pe4", Notation::english()),
+                   vec!["e4", "e5", "Re2", "e4"]);
+        assert_eq!(read("1. ♘f3 ♞f6 2. ♙e4 ♟e5 3. e8=♕+ a1=D", Notation::german()),
+                   vec!["Nf3", "Nf6", "e4", "e5", "e8=Q+", "a1=Q"]);
+        assert_eq!(read("1. ♘f3 ♞f6 2. e8=♕+", Notation::english()),
+                   vec!["Nf3", "Nf6", "e8=Q+"]);
+    }
+
+    #[test]
+    fn test_buffered() {
+        let pgn = "1. Sf3 Sf6 *\n\n1. Lb5 *";
+        let mut visitor = Moves(Vec::new());
+        let mut reader = BufferedReader::new(&mut visitor, BufReader::with_capacity(3, pgn.as_bytes()));
+        reader.notation(Notation::german());
+        let games = reader.into_iter().collect::<Result<Vec<_>, _>>().expect("read");
+        assert_eq!(games, vec![vec!["Nf3", "Nf6"], vec!["Bb5"]]);
+    }
+}
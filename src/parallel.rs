@@ -0,0 +1,438 @@
+// This file is part of the pgn-reader library.
+// Copyright (C) 2017 Niklas Fiekas <niklas.fiekas@backscattering.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt;
+use std::mem;
+use std::thread;
+use std::cmp::min;
+use std::collections::BTreeMap;
+use std::sync::{mpsc, Condvar, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use memchr;
+
+use {Reader, Visitor, Notation, Location, LineCounter};
+
+/// Number of chunks per thread that workers may run ahead of the consumer
+/// when results are passed on in order.
+const WINDOW: usize = 4;
+
+/// Reads a PGN on multiple threads.
+///
+/// The input is split into chunks at game boundaries, i.e. at a blank line
+/// followed by `[`. Each worker thread builds its own [`Visitor`] using
+/// the given factory and parses whole chunks with it.
+///
+/// If a visitor requests to stop, no further chunks are started. Since each
+/// thread has its own visitor, games from chunks that were already being
+/// read may still be passed to the consumer.
+///
+/// Unlike [`Reader`], this does not treat a blank line followed by `[` inside
+/// a `{ comment }` as part of the comment, if it happens to coincide with a
+/// chunk boundary.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{ParallelReader, Visitor, SanPlus};
+///
+/// struct MoveCounter(usize);
+///
+/// impl<'pgn> Visitor<'pgn> for MoveCounter {
+///     type Result = usize;
+///
+///     fn san(&mut self, _san_plus: SanPlus) {
+///         self.0 += 1;
+///     }
+///
+///     fn end_game(&mut self, _game: &'pgn [u8]) -> usize {
+///         ::std::mem::replace(&mut self.0, 0)
+///     }
+/// }
+///
+/// let pgn = b"[Event \"A\"]\n\n1. e4 e5 *\n\n[Event \"B\"]\n\n1. d4 *\n";
+///
+/// let mut moves = Vec::new();
+/// ParallelReader::new(pgn, || MoveCounter(0)).for_each(|m| moves.push(m));
+/// assert_eq!(moves, vec![2, 1]);
+/// ```
+///
+/// [`Visitor`]: trait.Visitor.html
+/// [`Reader`]: struct.Reader.html
+pub struct ParallelReader<'pgn, F> {
+    pgn: &'pgn [u8],
+    factory: F,
+    threads: usize,
+    chunk_size: usize,
+    notation: Notation,
+    recover: bool,
+}
+
+impl<'pgn, F> fmt::Debug for ParallelReader<'pgn, F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ParallelReader")
+            .field("threads", &self.threads)
+            .field("chunk_size", &self.chunk_size)
+            .finish()
+    }
+}
+
+impl<'pgn, F> ParallelReader<'pgn, F> {
+    /// Creates a new parallel reader. `factory` is called once per worker
+    /// thread to create a [`Visitor`].
+    ///
+    /// [`Visitor`]: trait.Visitor.html
+    pub fn new(pgn: &'pgn [u8], factory: F) -> ParallelReader<'pgn, F> {
+        ParallelReader {
+            pgn,
+            factory,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            chunk_size: 4 * 1024 * 1024,
+            notation: Notation::default(),
+            recover: false,
+        }
+    }
+
+    /// Sets the number of worker threads. Defaults to the available
+    /// parallelism.
+    pub fn threads(&mut self, threads: usize) -> &mut ParallelReader<'pgn, F> {
+        self.threads = threads;
+        self
+    }
+
+    /// Sets the approximate size of chunks in bytes. Defaults to 4 MiB.
+    pub fn chunk_size(&mut self, chunk_size: usize) -> &mut ParallelReader<'pgn, F> {
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    /// Sets the piece letters used in the movetext. Defaults to standard
+    /// English notation.
+    pub fn notation(&mut self, notation: Notation) -> &mut ParallelReader<'pgn, F> {
+        self.notation = notation;
+        self
+    }
+
+    /// Enables recovery from runaway comments and header lines, like
+    /// [`Reader::recover`]. Disabled by default.
+    ///
+    /// [`Reader::recover`]: struct.Reader.html#method.recover
+    pub fn recover(&mut self, recover: bool) -> &mut ParallelReader<'pgn, F> {
+        self.recover = recover;
+        self
+    }
+
+    /// Reads all games, passing the results to `consumer` in the order of
+    /// the games in the input. If a visitor requests to stop, the game
+    /// where it stopped is the last game passed to `consumer`.
+    ///
+    /// Results of chunks that finish early are held back until all previous
+    /// chunks are consumed. To bound the memory used for this, workers do
+    /// not start chunks more than `4 * threads` chunks ahead of the oldest
+    /// chunk that has not yet been consumed.
+    pub fn for_each<V, C>(&self, mut consumer: C)
+        where F: Fn() -> V + Sync,
+              V: Visitor<'pgn>,
+              V::Result: Send,
+              C: FnMut(V::Result)
+    {
+        let mut pending = BTreeMap::new();
+        let mut next = 0;
+        let mut done = false;
+
+        self.run(WINDOW, |index, results, stopped| {
+            pending.insert(index, (results, stopped));
+            while let Some((results, stopped)) = pending.remove(&next) {
+                if done {
+                    break;
+                }
+                results.into_iter().for_each(&mut consumer);
+                done = stopped;
+                next += 1;
+            }
+            next
+        });
+    }
+
+    /// Reads all games, passing the results to `consumer` as soon as they
+    /// are available.
+    pub fn for_each_unordered<V, C>(&self, mut consumer: C)
+        where F: Fn() -> V + Sync,
+              V: Visitor<'pgn>,
+              V::Result: Send,
+              C: FnMut(V::Result)
+    {
+        self.run(usize::MAX, |_, results, _| {
+            results.into_iter().for_each(&mut consumer);
+            0
+        });
+    }
+
+    /// Parses chunks on worker threads and passes their results to
+    /// `collect`, which returns the number of leading chunks that were
+    /// consumed. Workers do not start chunks `window * threads` or more
+    /// ahead of that.
+    fn run<V, C>(&self, window: usize, mut collect: C)
+        where F: Fn() -> V + Sync,
+              V: Visitor<'pgn>,
+              V::Result: Send,
+              C: FnMut(usize, Vec<V::Result>, bool) -> usize
+    {
+        let chunks = split_chunks(self.pgn, self.chunk_size);
+        let threads = min(self.threads.max(1), chunks.len());
+        let lines = count_lines(&chunks, threads);
+        let next_chunk = AtomicUsize::new(0);
+        let stopped = AtomicBool::new(false);
+        let window = window.saturating_mul(threads);
+        let consumed = Mutex::new(0usize);
+        let progress = Condvar::new();
+
+        thread::scope(|scope| {
+            let (tx, rx) = mpsc::sync_channel(threads * 2);
+
+            for _ in 0..threads {
+                let tx = tx.clone();
+                let chunks = &chunks;
+                let lines = &lines;
+                let next_chunk = &next_chunk;
+                let stopped = &stopped;
+                let consumed = &consumed;
+                let progress = &progress;
+                scope.spawn(move || {
+                    let mut visitor = (self.factory)();
+                    while !stopped.load(Ordering::Relaxed) {
+                        let index = next_chunk.fetch_add(1, Ordering::Relaxed);
+                        let (offset, chunk) = match chunks.get(index) {
+                            Some(&chunk) => chunk,
+                            None => break,
+                        };
+
+                        let mut done = consumed.lock().unwrap();
+                        while index >= (*done).saturating_add(window) && !stopped.load(Ordering::Relaxed) {
+                            done = progress.wait(done).unwrap();
+                        }
+                        drop(done);
+                        if stopped.load(Ordering::Relaxed) {
+                            break;
+                        }
+
+                        let start = LineCounter::new(offset, Location { line: lines[index], column: 1 });
+                        let mut reader = Reader::with_offset(&mut visitor, chunk, start);
+                        reader.notation(self.notation).recover(self.recover);
+                        let mut results = Vec::new();
+                        while let Some(result) = reader.read_game() {
+                            results.push(result);
+                        }
+                        if reader.stopped() {
+                            stopped.store(true, Ordering::Relaxed);
+                        }
+                        if tx.send((index, results, reader.stopped())).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+
+            drop(tx);
+
+            let _wake = Wake { stopped: &stopped, consumed: &consumed, progress: &progress };
+
+            for (index, results, stopped) in rx {
+                *consumed.lock().unwrap() = collect(index, results, stopped);
+                progress.notify_all();
+            }
+        });
+    }
+}
+
+/// Releases workers waiting for the consumer when dropped, in particular if
+/// the consumer panics.
+struct Wake<'a> {
+    stopped: &'a AtomicBool,
+    consumed: &'a Mutex<usize>,
+    progress: &'a Condvar,
+}
+
+impl<'a> Drop for Wake<'a> {
+    fn drop(&mut self) {
+        let _consumed = self.consumed.lock();
+        self.stopped.store(true, Ordering::Relaxed);
+        self.progress.notify_all();
+    }
+}
+
+/// Splits the input into chunks of approximately `chunk_size` bytes, at
+/// game boundaries. Returns the offset of each chunk along with the chunk.
+fn split_chunks(pgn: &[u8], chunk_size: usize) -> Vec<(usize, &[u8])> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+
+    while start < pgn.len() {
+        let end = next_boundary(pgn, start.saturating_add(chunk_size));
+        chunks.push((start, &pgn[start..end]));
+        start = end;
+    }
+
+    chunks
+}
+
+/// Finds the line number at the start of each chunk. Chunks start at the
+/// beginning of a line. The lines are counted on up to `threads` threads.
+fn count_lines(chunks: &[(usize, &[u8])], threads: usize) -> Vec<usize> {
+    let mut lines = vec![0; chunks.len()];
+    let per_thread = chunks.len().div_ceil(threads.max(1)).max(1);
+
+    thread::scope(|scope| {
+        for (chunks, lines) in chunks.chunks(per_thread).zip(lines.chunks_mut(per_thread)) {
+            scope.spawn(move || {
+                for (&(_, chunk), lines) in chunks.iter().zip(lines) {
+                    *lines = memchr::memchr_iter(b'\n', chunk).count();
+                }
+            });
+        }
+    });
+
+    // Turn the number of lines in each chunk into the line it starts at.
+    let mut line = 1;
+    for lines in &mut lines {
+        line += mem::replace(lines, line);
+    }
+    lines
+}
+
+/// Finds the first blank line followed by `[` at or after `pos`, and returns
+/// the position of the `[`.
+fn next_boundary(pgn: &[u8], mut pos: usize) -> usize {
+    while pos < pgn.len() {
+        pos = match memchr::memchr(b'\n', &pgn[pos..]) {
+            Some(delta) => pos + delta + 1,
+            None => break,
+        };
+
+        let rest = &pgn[pos..];
+        if rest.starts_with(b"\n[") {
+            return pos + 1;
+        } else if rest.starts_with(b"\r\n[") {
+            return pos + 2;
+        }
+    }
+
+    pgn.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Headers(Location);
+
+    impl<'pgn> Visitor<'pgn> for Headers {
+        type Result = (&'pgn [u8], Location);
+
+        fn span(&mut self, span: ::Span) {
+            self.0 = span.location;
+        }
+
+        fn end_game(&mut self, game: &'pgn [u8]) -> Self::Result {
+            (game, self.0)
+        }
+    }
+
+    #[test]
+    fn test_chunks() {
+        let pgn = b"[Event \"1\"]\n\n1. e4 *\n\n[Event \"2\"]\r\n\r\n1. d4 *\r\n\r\n[Event \"3\"]\n\n*\n";
+
+        let mut sequential = Vec::new();
+        Reader::new(&mut Headers(Location::default()), &pgn[..]).into_iter().for_each(|g| sequential.push(g));
+        assert_eq!(sequential.len(), 3);
+        assert_eq!(sequential[2].1, Location { line: 9, column: 1 });
+
+        for chunk_size in (0..pgn.len() + 1).chain(Some(usize::MAX)) {
+            let mut ordered = Vec::new();
+            ParallelReader::new(&pgn[..], || Headers(Location::default()))
+                .chunk_size(chunk_size)
+                .threads(3)
+                .for_each(|g| ordered.push(g));
+            assert_eq!(ordered, sequential);
+
+            let mut unordered = Vec::new();
+            ParallelReader::new(&pgn[..], || Headers(Location::default()))
+                .chunk_size(chunk_size)
+                .for_each_unordered(|g| unordered.push(g));
+            unordered.sort();
+            assert_eq!(unordered, sequential);
+        }
+    }
+
+    struct StopFirst;
+
+    impl<'pgn> Visitor<'pgn> for StopFirst {
+        type Result = &'pgn [u8];
+
+        fn end_game(&mut self, game: &'pgn [u8]) -> &'pgn [u8] {
+            game
+        }
+
+        fn stop(&mut self) -> ::Stop {
+            ::Stop(true)
+        }
+    }
+
+    #[test]
+    fn test_stop() {
+        let pgn = b"1. e4 *\n\n[Event \"?\"]\n1. d4 *\n\n[Event \"?\"]\n1. c4 *\n";
+
+        for chunk_size in 0..pgn.len() + 1 {
+            let mut games = Vec::new();
+            ParallelReader::new(&pgn[..], || StopFirst)
+                .chunk_size(chunk_size)
+                .for_each(|g| games.push(g));
+            assert_eq!(games, vec![&b"1. e4 *\n\n"[..]]);
+        }
+    }
+
+    struct SlowStart<'a> {
+        begun: &'a AtomicUsize,
+    }
+
+    impl<'a, 'pgn> Visitor<'pgn> for SlowStart<'a> {
+        type Result = ();
+
+        fn header(&mut self, _key: &'pgn [u8], _value: ::RawHeader<'pgn>) {
+            if self.begun.fetch_add(1, Ordering::SeqCst) == 0 {
+                thread::sleep(::std::time::Duration::from_millis(50));
+            }
+        }
+
+        fn end_game(&mut self, _game: &'pgn [u8]) { }
+    }
+
+    #[test]
+    fn test_window() {
+        let pgn = b"[Event \"?\"]\n\n*\n\n".repeat(64);
+
+        let begun = AtomicUsize::new(0);
+        let mut consumed = 0;
+        ParallelReader::new(&pgn[..], || SlowStart { begun: &begun })
+            .chunk_size(0)
+            .threads(2)
+            .for_each(|()| {
+                assert!(begun.load(Ordering::SeqCst) <= consumed + WINDOW * 2);
+                consumed += 1;
+            });
+        assert_eq!(consumed, 64);
+    }
+}
@@ -0,0 +1,550 @@
+// This file is part of the pgn-reader library.
+// Copyright (C) 2017 Niklas Fiekas <niklas.fiekas@backscattering.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt;
+use std::mem;
+use std::error::Error;
+
+use shakmaty::{Chess, Move, Position};
+use shakmaty::fen::Fen;
+use shakmaty::san::san_plus;
+
+use {Visitor, RawHeader, SanPlus, San, Uci, Nag, Outcome, Color, Span, SyntaxError, LineCounter};
+
+/// The Seven Tag Roster, in the required order.
+const SEVEN_TAG_ROSTER: [&str; 7] = ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+/// Maximum length of a line in export format, excluding the line break.
+const MAX_LINE_LEN: usize = 255;
+
+/// A kind of deviation from the PGN export format.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum ViolationKind {
+    /// A tag of the Seven Tag Roster is missing.
+    MissingTag(&'static str),
+    /// A tag of the Seven Tag Roster is out of order, or follows some other
+    /// tag.
+    MisorderedTag,
+    /// A tag name contains characters other than letters, digits and `_`.
+    InvalidTagName,
+    /// The `Result` tag is not one of `1-0`, `0-1`, `1/2-1/2` or `*`.
+    InvalidResult,
+    /// A move is not preceded by the required move number indication.
+    MissingMoveNumber,
+    /// A move number indication does not match the move.
+    WrongMoveNumber,
+    /// A move that is not written exactly in SAN, like a move in long
+    /// algebraic or coordinate notation, `0-0`, a figurine, a localized piece
+    /// letter, a redundant disambiguation or a missing check suffix.
+    NotSan,
+    /// A null move `--`.
+    NullMove,
+    /// A suffix annotation like `!` or `?!` rather than a NAG like `$1`.
+    SuffixAnnotation,
+    /// The game has no termination marker.
+    MissingTermination,
+    /// The game has more than one termination marker.
+    MultipleTerminations,
+    /// The termination marker is followed by other movetext.
+    TerminationNotLast,
+    /// The termination marker does not match the `Result` tag.
+    ResultMismatch,
+    /// A line is longer than 255 bytes.
+    LineTooLong,
+    /// A `%` escape line.
+    EscapeLine,
+    /// A token that the reader could not understand.
+    SyntaxError(SyntaxError),
+}
+
+impl fmt::Display for ViolationKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ViolationKind::MissingTag(name) => write!(f, "missing {} tag", name),
+            ViolationKind::MisorderedTag => "misordered tag".fmt(f),
+            ViolationKind::InvalidTagName => "invalid tag name".fmt(f),
+            ViolationKind::InvalidResult => "invalid result tag".fmt(f),
+            ViolationKind::MissingMoveNumber => "missing move number".fmt(f),
+            ViolationKind::WrongMoveNumber => "wrong move number".fmt(f),
+            ViolationKind::NotSan => "move not in standard algebraic notation".fmt(f),
+            ViolationKind::NullMove => "null move".fmt(f),
+            ViolationKind::SuffixAnnotation => "suffix annotation instead of nag".fmt(f),
+            ViolationKind::MissingTermination => "missing game termination marker".fmt(f),
+            ViolationKind::MultipleTerminations => "multiple game termination markers".fmt(f),
+            ViolationKind::TerminationNotLast => "game termination marker is not the last token".fmt(f),
+            ViolationKind::ResultMismatch => "game termination marker does not match result tag".fmt(f),
+            ViolationKind::LineTooLong => "line too long".fmt(f),
+            ViolationKind::EscapeLine => "escape line".fmt(f),
+            ViolationKind::SyntaxError(err) => err.fmt(f),
+        }
+    }
+}
+
+/// A deviation from the PGN export format and its location.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct Violation {
+    pub kind: ViolationKind,
+    pub span: Span,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.kind.fmt(f)
+    }
+}
+
+impl Error for Violation {
+    fn description(&self) -> &str {
+        "pgn export format violation"
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Token {
+    Header,
+    MoveNumber(u32, Color),
+    San(SanPlus),
+    Uci(Uci),
+    Nag,
+    Comment,
+    BeginVariation,
+    EndVariation,
+    Outcome(Option<Outcome>),
+    Other,
+}
+
+/// A visitor that checks games against the PGN export format.
+///
+/// Checks that the Seven Tag Roster is present and in order, tag names are
+/// legal, moves are written exactly in SAN and preceded by correct move
+/// number indications, there are no null moves, annotations are NAGs,
+/// there is exactly one game termination marker at the end of the movetext
+/// and it matches the `Result` tag, lines are at most 255 bytes long, and
+/// there are no `%` escape lines. Tokens reported via
+/// [`Visitor::syntax_error`] are also violations.
+///
+/// Moves are played from the starting position or the `FEN` tag, so that
+/// redundant disambiguations and missing check suffixes are found. Moves
+/// of a line after an illegal move are only checked for their notation.
+///
+/// Returns all violations of a game, in no particular order.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{Reader, ExportValidator, ViolationKind};
+///
+/// let pgn = b"[Event \"?\"]\n[Site \"?\"]\n[Date \"????.??.??\"]\n[Round \"?\"]\n\
+///             [White \"?\"]\n[Black \"?\"]\n[Result \"1-0\"]\n\
+///             \n\
+///             1. e4 e5 Nf3 0-1";
+///
+/// let mut validator = ExportValidator::new();
+/// let mut violations = Reader::new(&mut validator, pgn).read_game().unwrap();
+/// violations.sort_by_key(|v| v.span.start);
+///
+/// let kinds: Vec<_> = violations.iter().map(|v| v.kind).collect();
+/// assert_eq!(kinds, vec![ViolationKind::MissingMoveNumber, ViolationKind::ResultMismatch]);
+/// assert_eq!(&pgn[violations[0].span.start..violations[0].span.end], b"Nf3");
+/// ```
+///
+/// [`Visitor::syntax_error`]: trait.Visitor.html#method.syntax_error
+#[derive(Debug, Default)]
+pub struct ExportValidator {
+    span: Span,
+    seen: [bool; 7],
+    max_seen: Option<usize>,
+    other_tag: bool,
+    result: Option<Option<Outcome>>,
+    start_ply: usize,
+    start_pos: Option<Chess>,
+    tokens: Vec<(Token, Span)>,
+    violations: Vec<Violation>,
+}
+
+impl ExportValidator {
+    pub fn new() -> ExportValidator {
+        ExportValidator::default()
+    }
+
+    fn violation(&mut self, kind: ViolationKind, span: Span) {
+        self.violations.push(Violation { kind, span });
+    }
+
+    fn token(&mut self, token: Token) {
+        let span = self.span;
+        self.tokens.push((token, span));
+    }
+
+    fn check_lines(&mut self, game: &[u8], start: LineCounter) {
+        let mut lines = start;
+        for line in game.split(|c| *c == b'\n') {
+            let len = if line.ends_with(b"\r") { line.len() - 1 } else { line.len() };
+            let span = Span { start: lines.pos, end: lines.pos + len, location: lines.location() };
+            if len > MAX_LINE_LEN {
+                self.violation(ViolationKind::LineTooLong, span);
+            }
+            if line.starts_with(b"%") {
+                self.violation(ViolationKind::EscapeLine, span);
+            }
+            lines.advance(line);
+            lines.advance(b"\n");
+        }
+    }
+
+    fn check_movetext(&mut self, game: &[u8], start: LineCounter) {
+        let offset = start.pos;
+        let tokens = mem::take(&mut self.tokens);
+
+        let mut prev_end = offset;
+        let mut numbers = Vec::new();
+        let mut terminations = Vec::new();
+        let mut ply = self.start_ply;
+        let mut variations = Vec::new();
+        let mut interrupted = true;
+
+        // The current position, if known, and the position before the last
+        // move, where a variation starts.
+        let mut pos = self.start_pos.clone();
+        let mut before = None;
+
+        for &(ref token, span) in &tokens {
+            prev_end = span.end;
+
+            match *token {
+                Token::Header => (),
+                Token::MoveNumber(number, color) => {
+                    // Black move numbers are indicated by exactly three dots.
+                    let text = &game[span.start - offset..span.end - offset];
+                    let dots = text.iter().rev().take_while(|c| **c == b'.').count();
+                    let number = if color == Color::Black && dots != 3 { 0 } else { number };
+                    numbers.push(((number, color), span));
+                },
+                Token::San(_) | Token::Uci(_) => {
+                    let text = &game[span.start - offset..span.end - offset];
+                    let p = pos.take();
+                    pos = match *token {
+                        Token::San(SanPlus { san: San::Null, .. }) => {
+                            self.violation(ViolationKind::NullMove, span);
+                            p.clone().and_then(|p| p.swap_turn().ok())
+                        },
+                        Token::San(ref parsed) => {
+                            let m = p.as_ref().and_then(|p| parsed.san.to_move(p).ok());
+                            let expected = match (&p, &m) {
+                                (Some(p), Some(m)) => san_plus(p.clone(), m),
+                                _ => parsed.clone(),
+                            };
+                            if text != expected.to_string().as_bytes() {
+                                self.violation(ViolationKind::NotSan, span);
+                            }
+                            play(p.as_ref(), m)
+                        },
+                        Token::Uci(ref uci) => play(p.as_ref(), p.as_ref().and_then(|p| uci.to_move(p).ok())),
+                        _ => None,
+                    };
+                    before = p;
+
+                    let expected = ((ply / 2 + 1) as u32, if ply.is_multiple_of(2) { Color::White } else { Color::Black });
+                    match numbers.pop() {
+                        Some((number, _)) if number == expected => (),
+                        Some((_, number_span)) =>
+                            self.violation(ViolationKind::WrongMoveNumber, number_span),
+                        None if expected.1 == Color::White || interrupted =>
+                            self.violation(ViolationKind::MissingMoveNumber, span),
+                        None => (),
+                    }
+                    numbers.clear();
+                    ply += 1;
+                    interrupted = false;
+                },
+                Token::Nag => {
+                    if game.get(span.start - offset) != Some(&b'$') {
+                        self.violation(ViolationKind::SuffixAnnotation, span);
+                    }
+                },
+                Token::Comment => interrupted = true,
+                Token::BeginVariation => {
+                    let start = before.clone().or_else(|| pos.clone());
+                    variations.push((ply, mem::replace(&mut pos, start), before.take()));
+                    ply = ply.saturating_sub(1);
+                    interrupted = true;
+                },
+                Token::EndVariation => {
+                    if let Some((outer_ply, outer_pos, outer_before)) = variations.pop() {
+                        ply = outer_ply;
+                        pos = outer_pos;
+                        before = outer_before;
+                    }
+                    interrupted = true;
+                },
+                Token::Outcome(ref outcome) => terminations.push((outcome.clone(), span)),
+                Token::Other => (),
+            }
+        }
+
+        // Only further markers may follow the first one.
+        let first = tokens.iter().position(|(token, _)| matches!(*token, Token::Outcome(_)));
+        if let Some(first) = first {
+            if tokens[first + 1..].iter().any(|(token, _)| !matches!(*token, Token::Outcome(_))) {
+                self.violation(ViolationKind::TerminationNotLast, tokens[first].1);
+            }
+        }
+
+        match terminations.split_first() {
+            Some((&(ref outcome, span), others)) => {
+                if self.result.as_ref().is_some_and(|result| result != outcome) {
+                    self.violation(ViolationKind::ResultMismatch, span);
+                }
+                for &(_, span) in others {
+                    self.violation(ViolationKind::MultipleTerminations, span);
+                }
+            },
+            None => {
+                let mut lines = start;
+                lines.advance(&game[..prev_end - offset]);
+                let end = Span { start: prev_end, end: prev_end, location: lines.location() };
+                self.violation(ViolationKind::MissingTermination, end);
+            },
+        }
+
+        self.tokens = tokens;
+    }
+}
+
+/// Plays a move, if both the position and the move are known.
+fn play(pos: Option<&Chess>, m: Option<Move>) -> Option<Chess> {
+    let mut after = pos?.clone();
+    after.play_unchecked(&m?);
+    Some(after)
+}
+
+fn is_tag_name(key: &[u8]) -> bool {
+    !key.is_empty() && key.iter().all(|c| c.is_ascii_alphanumeric() || *c == b'_')
+}
+
+impl<'pgn> Visitor<'pgn> for ExportValidator {
+    type Result = Vec<Violation>;
+
+    fn begin_game(&mut self) {
+        self.seen = [false; 7];
+        self.max_seen = None;
+        self.other_tag = false;
+        self.result = None;
+        self.start_ply = 0;
+        self.start_pos = Some(Chess::default());
+        self.tokens.clear();
+        self.violations.clear();
+    }
+
+    fn span(&mut self, span: Span) {
+        self.span = span;
+    }
+
+    fn header(&mut self, key: &'pgn [u8], value: RawHeader<'pgn>) {
+        self.token(Token::Header);
+
+        let span = self.span;
+        if !is_tag_name(key) {
+            self.violation(ViolationKind::InvalidTagName, span);
+        }
+
+        match SEVEN_TAG_ROSTER.iter().position(|name| name.as_bytes() == key) {
+            Some(index) => {
+                if self.other_tag || self.max_seen.is_some_and(|max| index < max) {
+                    self.violation(ViolationKind::MisorderedTag, span);
+                }
+                self.seen[index] = true;
+                self.max_seen = Some(self.max_seen.map_or(index, |max| max.max(index)));
+            },
+            None => self.other_tag = true,
+        }
+
+        if key == b"Result" {
+            self.result = value.outcome().ok();
+            if self.result.is_none() {
+                self.violation(ViolationKind::InvalidResult, span);
+            }
+        } else if key == b"FEN" {
+            if let Ok(fen) = Fen::from_bytes(value.as_bytes()) {
+                let fullmoves = fen.fullmoves.max(1) as usize;
+                self.start_ply = (fullmoves - 1) * 2 + fen.turn.fold(0, 1);
+                self.start_pos = fen.position().ok();
+            } else {
+                self.start_pos = None;
+            }
+        }
+    }
+
+    fn move_number(&mut self, number: u32, color: Color) {
+        self.token(Token::MoveNumber(number, color));
+    }
+
+    fn san(&mut self, san_plus: SanPlus) {
+        self.token(Token::San(san_plus));
+    }
+
+    fn uci(&mut self, uci: Uci) {
+        self.token(Token::Uci(uci));
+        let span = self.span;
+        self.violation(ViolationKind::NotSan, span);
+    }
+
+    fn nag(&mut self, _nag: Nag) {
+        self.token(Token::Nag);
+    }
+
+    fn comment(&mut self, _comment: &'pgn [u8]) {
+        self.token(Token::Comment);
+    }
+
+    fn begin_variation(&mut self) -> ::Skip {
+        self.token(Token::BeginVariation);
+        ::Skip(false)
+    }
+
+    fn end_variation(&mut self) {
+        self.token(Token::EndVariation);
+    }
+
+    fn outcome(&mut self, outcome: Option<Outcome>) {
+        self.token(Token::Outcome(outcome));
+    }
+
+    fn syntax_error(&mut self, error: SyntaxError, _token: &'pgn [u8]) {
+        self.token(Token::Other);
+        let span = self.span;
+        self.violation(ViolationKind::SyntaxError(error), span);
+    }
+
+    fn end_game(&mut self, game: &'pgn [u8]) -> Self::Result {
+        let start = LineCounter::new(self.span.start, self.span.location);
+
+        for (index, name) in SEVEN_TAG_ROSTER.iter().enumerate() {
+            if !self.seen[index] {
+                let span = Span { start: start.pos, end: start.pos, location: start.location() };
+                self.violation(ViolationKind::MissingTag(name), span);
+            }
+        }
+
+        self.check_lines(game, start);
+        self.check_movetext(game, start);
+
+        mem::take(&mut self.violations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Reader;
+
+    fn violations(pgn: &[u8]) -> Vec<(ViolationKind, &[u8])> {
+        let mut validator = ExportValidator::new();
+        let mut violations = Reader::new(&mut validator, pgn).read_game().expect("game");
+        violations.sort_by_key(|v| v.span.start);
+        violations.iter().map(|v| (v.kind, &pgn[v.span.start..v.span.end])).collect()
+    }
+
+    #[test]
+    fn test_valid() {
+        let pgn = b"[Event \"F/S Return Match\"]\n\
+                    [Site \"Belgrade, Serbia JUG\"]\n\
+                    [Date \"1992.11.04\"]\n\
+                    [Round \"29\"]\n\
+                    [White \"Fischer, Robert J.\"]\n\
+                    [Black \"Spassky, Boris V.\"]\n\
+                    [Result \"1/2-1/2\"]\n\
+                    [FEN \"rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 7\"]\n\
+                    \n\
+                    7... e5 8. Nf3 $1 Nc6 { comment } 9. Bb5 (9. Bc4 Nf6 (9... a6)\n\
+                    10. d3) 9... a6 1/2-1/2\n";
+
+        assert_eq!(violations(pgn), vec![]);
+    }
+
+    fn movetext_violations(movetext: &[u8]) -> Vec<(ViolationKind, Vec<u8>)> {
+        let mut pgn = b"[Event \"?\"]\n[Site \"?\"]\n[Date \"????.??.??\"]\n[Round \"?\"]\n\
+                        [White \"?\"]\n[Black \"?\"]\n[Result \"*\"]\n\n".to_vec();
+        pgn.extend_from_slice(movetext);
+        violations(&pgn).into_iter().map(|(kind, text)| (kind, text.to_vec())).collect()
+    }
+
+    #[test]
+    fn test_export_san() {
+        assert_eq!(movetext_violations(b"1. Nf3 Nf6 2. g3 g6 3. Bg2 Bg7 4. O-O O-O *"), vec![]);
+
+        for &(movetext, kind, token) in &[
+            (&b"1. 0-0 *"[..], ViolationKind::NotSan, &b"0-0"[..]),
+            (b"1. Ng1f3 *", ViolationKind::NotSan, b"Ng1f3"),
+            (b"1. -- *", ViolationKind::NullMove, b"--"),
+            (b"1. \xe2\x99\x98f3 *", ViolationKind::NotSan, b"\xe2\x99\x98f3"),
+            (b"1. Nf3! *", ViolationKind::SuffixAnnotation, b"!"),
+            (b"1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7 *", ViolationKind::NotSan, b"Qxf7"),
+            (b"1. e4 * { late }", ViolationKind::TerminationNotLast, b"*"),
+        ] {
+            assert_eq!(movetext_violations(movetext), vec![(kind, token.to_vec())]);
+        }
+
+        // Variations start from the position before the move they replace,
+        // even if that move is illegal.
+        assert_eq!(movetext_violations(b"1. e4 e5 2. Nd3 (2. Ng1f3) *"), vec![
+            (ViolationKind::NotSan, b"Ng1f3".to_vec()),
+        ]);
+
+        let pgn = b"[Event \"?\"]\n[Site \"?\"]\n[Date \"????.??.??\"]\n[Round \"?\"]\n\
+                    [White \"?\"]\n[Black \"?\"]\n[Result \"*\"]\n\n1. Sf3 *";
+        let mut validator = ExportValidator::new();
+        let violations = Reader::new(&mut validator, &pgn[..]).notation(::Notation::german()).read_game().expect("game");
+        assert_eq!(violations.iter().map(|v| v.kind).collect::<Vec<_>>(), vec![ViolationKind::NotSan]);
+    }
+
+    #[test]
+    fn test_violations() {
+        let pgn = b"[Site \"?\"]\n\
+                    [Event \"?\"]\n\
+                    [Round_1 \"?\"]\n\
+                    [Bad-Tag \"?\"]\n\
+                    [Result \"1-0\"]\n\
+                    \n\
+                    1. e4 { x } e5 3. g1f3 (2. d4 2. d5) 2... Bf8-c5 *\n\
+                    %escape\n\
+                    0-1";
+
+        assert_eq!(violations(pgn), vec![
+            (ViolationKind::MissingTag("Date"), &b""[..]),
+            (ViolationKind::MissingTag("Round"), b""),
+            (ViolationKind::MissingTag("White"), b""),
+            (ViolationKind::MissingTag("Black"), b""),
+            (ViolationKind::MisorderedTag, b"[Event \"?\"]"),
+            (ViolationKind::InvalidTagName, b"[Bad-Tag \"?\"]"),
+            (ViolationKind::MisorderedTag, b"[Result \"1-0\"]"),
+            (ViolationKind::MissingMoveNumber, b"e5"),
+            (ViolationKind::WrongMoveNumber, b"3."),
+            (ViolationKind::NotSan, b"g1f3"),
+            (ViolationKind::WrongMoveNumber, b"2."),
+            (ViolationKind::NotSan, b"Bf8-c5"),
+            (ViolationKind::ResultMismatch, b"*"),
+            (ViolationKind::EscapeLine, b"%escape"),
+            (ViolationKind::MultipleTerminations, b"0-1"),
+        ]);
+
+        let mut validator = ExportValidator::new();
+        let violations = Reader::new(&mut validator, &pgn[..]).read_game().expect("game");
+        let location = |kind| violations.iter().find(|v| v.kind == kind).map(|v| v.span.location);
+        assert_eq!(location(ViolationKind::EscapeLine), Some(::Location { line: 8, column: 1 }));
+        assert_eq!(location(ViolationKind::NotSan), Some(::Location { line: 7, column: 19 }));
+    }
+}